@@ -108,7 +108,7 @@ fn bench_note_decryption(c: &mut Criterion) {
     {
         // Benchmark with 2 IVKs to emulate a wallet with two pools of funds.
         let ivks = 2;
-        let valid_ivks = vec![valid_ivk; ivks];
+        let valid_ivks = vec![valid_ivk.clone(); ivks];
         let actions: Vec<_> = (0..100)
             .map(|_| (OrchardDomain::for_action(action), action.clone()))
             .collect();
@@ -145,6 +145,43 @@ fn bench_note_decryption(c: &mut Criterion) {
             });
         }
     }
+
+    #[cfg(feature = "multicore")]
+    {
+        use orchard::{
+            note_encryption::scan::{scan_compact_bundles, ParallelScanner},
+            ValuePool,
+        };
+
+        // A range of compact blocks with one 8-action bundle each, scanned with 2 IVKs.
+        let ivks = [valid_ivk.clone(), invalid_ivks[0].clone()];
+        let bundles: Vec<(ValuePool, Vec<CompactAction>)> = (0..256)
+            .map(|_| (ValuePool::Orchard, vec![CompactAction::from(action); 8]))
+            .collect();
+        let borrowed = || {
+            bundles
+                .iter()
+                .map(|(pool, actions)| (*pool, actions.as_slice()))
+        };
+        let actions = bundles
+            .iter()
+            .map(|(_, actions)| actions.len())
+            .sum::<usize>();
+
+        let mut group = c.benchmark_group("parallel-compact-scan");
+        group.throughput(Throughput::Elements((ivks.len() * actions) as u64));
+        group.sample_size(10);
+
+        group.bench_function("sequential", |b| {
+            b.iter(|| scan_compact_bundles(&ivks, borrowed()))
+        });
+
+        let scanner = ParallelScanner::new(&ivks);
+        group.bench_function("parallel", |b| b.iter(|| scanner.scan(borrowed())));
+        group.bench_function("stream", |b| {
+            b.iter(|| scanner.scan_stream(bundles.iter().cloned()).count())
+        });
+    }
 }
 
 #[cfg(unix)]
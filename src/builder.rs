@@ -8,7 +8,7 @@ use core::iter;
 use ff::Field;
 use pasta_curves::pallas;
 use rand::{prelude::SliceRandom, CryptoRng, RngCore};
-use zcash_note_encryption::ENC_CIPHERTEXT_SIZE;
+use zcash_note_encryption::{EphemeralKeyBytes, ENC_CIPHERTEXT_SIZE};
 
 use crate::{
     address::Address,
@@ -18,7 +18,7 @@ use crate::{
         SpendingKey,
     },
     note::{ExtractedNoteCommitment, Note, NoteVersion, Nullifier, Rho, TransmittedNoteCiphertext},
-    note_encryption::OrchardNoteEncryption,
+    note_encryption::{prf_ock_orchard, OrchardNoteEncryption},
     primitives::redpallas::{self, Binding, SpendAuth},
     tree::{Anchor, MerklePath},
     value::{self, BalanceError, NoteValue, ValueCommitTrapdoor, ValueCommitment, ValueSum},
@@ -34,6 +34,9 @@ use {
     nonempty::NonEmpty,
 };
 
+pub mod migration;
+pub mod planner;
+
 const DEFAULT_MIN_ACTIONS: u8 = 2;
 
 /// An enumeration of rules for Orchard bundle construction.
@@ -85,9 +88,9 @@ impl BundleType {
     /// Unpadded transactional bundle: the bundle is padded only to the
     /// one-action consensus minimum, so it contains exactly the requested
     /// actions. Intended for transactions whose shape is already public — such
-    /// as pool migrations, where the per-pool value balances reveal the
-    /// transfer — since the action count reveals the transaction shape (see
-    /// [`BundleType::Transactional::pad_to_minimum`]).
+    /// as pool migrations (see [`migration`]), where the per-pool value balances
+    /// reveal the transfer — since the action count reveals the transaction shape
+    /// (see [`BundleType::Transactional::pad_to_minimum`]).
     pub const UNPADDED: BundleType = BundleType::Transactional {
         bundle_required: false,
         pad_to_minimum: Some(1),
@@ -101,7 +104,7 @@ impl BundleType {
     /// action (each is paired with a fabricated zero-valued counterpart), so the number of
     /// requested actions is `num_spends + num_outputs` rather than `max(num_spends, num_outputs)`.
     /// Wallets estimating fees (e.g. per [ZIP 317]) must account for this larger action
-    /// count.
+    /// count; [`planner::Planner`] does so when selecting notes.
     ///
     /// Returns an error if the specified number of spends and outputs is incompatible with
     /// this bundle type and flags.
@@ -508,13 +511,13 @@ impl OutputInfo {
         recipient: Address,
         value: NoteValue,
         note_version: NoteVersion,
-        memo: [u8; 512],
+        memo: impl Into<[u8; 512]>,
     ) -> Self {
         Self {
             ovk,
             recipient,
             value,
-            memo,
+            memo: memo.into(),
             note_version,
             randomized_ciphertext: false,
         }
@@ -604,6 +607,17 @@ impl OutputInfo {
     ) -> crate::pczt::Output {
         let (note, cmx, encrypted_note) = self.build(cv_net, nf_old, rng);
 
+        // This is the `ock` the encryptor derived to encrypt `out_ciphertext`, so that
+        // Signers can check it.
+        let ock = self.ovk.as_ref().map(|ovk| {
+            prf_ock_orchard(
+                ovk,
+                cv_net,
+                &cmx.to_bytes(),
+                &EphemeralKeyBytes(encrypted_note.epk_bytes),
+            )
+        });
+
         crate::pczt::Output {
             cmx,
             note_version: self.note_version,
@@ -611,9 +625,7 @@ impl OutputInfo {
             recipient: Some(self.recipient),
             value: Some(self.value),
             rseed: Some(*note.rseed()),
-            // TODO: Extract ock from the encryptor and save it so
-            // Signers can check `out_ciphertext`.
-            ock: None,
+            ock,
             zip32_derivation: None,
             user_address: None,
             proprietary: BTreeMap::new(),
@@ -649,7 +661,7 @@ impl ChangeInfo {
         recipient: Address,
         value: NoteValue,
         note_version: NoteVersion,
-        memo: [u8; 512],
+        memo: impl Into<[u8; 512]>,
     ) -> Result<Self, OutputError> {
         let scope = fvk
             .scope_for_address(&recipient)
@@ -1010,6 +1022,8 @@ impl Builder {
 
     /// Adds an address which will receive funds in this transaction.
     ///
+    /// `memo` is either a [`Memo`](crate::memo::Memo) or a raw memo field.
+    ///
     /// In a bundle that disables cross-address transfers, ordinary outputs cannot be
     /// constructed (each action's output is addressed to the note it spends); retained
     /// value must be added with [`Builder::add_change_output`] instead.
@@ -1018,7 +1032,7 @@ impl Builder {
         ovk: Option<OutgoingViewingKey>,
         recipient: Address,
         value: NoteValue,
-        memo: [u8; 512],
+        memo: impl Into<[u8; 512]>,
     ) -> Result<(), OutputError> {
         if !self.flags.outputs_enabled() {
             return Err(OutputError::OutputsDisabled);
@@ -1067,7 +1081,7 @@ impl Builder {
         ovk: Option<OutgoingViewingKey>,
         recipient: Address,
         value: NoteValue,
-        memo: [u8; 512],
+        memo: impl Into<[u8; 512]>,
     ) -> Result<(), OutputError> {
         if !self.flags.outputs_enabled() {
             return Err(OutputError::OutputsDisabled);
@@ -1642,6 +1656,24 @@ pub struct SigningParts {
     alpha: pallas::Scalar,
 }
 
+impl SigningParts {
+    /// Returns the spend validating key for this action.
+    pub fn ak(&self) -> &SpendValidatingKey {
+        &self.ak
+    }
+
+    /// Returns the randomizer that, applied to the spend authorizing key, derives the
+    /// signing key for this action.
+    ///
+    /// A wallet that will sign a [`PaymentDisclosure`] for the bundle must retain this
+    /// value.
+    ///
+    /// [`PaymentDisclosure`]: crate::bundle::PaymentDisclosure
+    pub fn alpha(&self) -> &pallas::Scalar {
+        &self.alpha
+    }
+}
+
 /// Marker for an unauthorized bundle with no signatures.
 #[derive(Clone, Debug)]
 pub struct Unauthorized {
@@ -1664,6 +1696,13 @@ pub struct SigningMetadata {
     parts: SigningParts,
 }
 
+impl SigningMetadata {
+    /// Returns the parts needed to sign this action.
+    pub fn parts(&self) -> &SigningParts {
+        &self.parts
+    }
+}
+
 /// Marker for a partially-authorized bundle, in the process of being signed.
 #[derive(Debug)]
 pub struct PartiallyAuthorized {
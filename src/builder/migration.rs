@@ -0,0 +1,345 @@
+//! Migration of notes from the Orchard pool to the Ironwood pool.
+//!
+//! A migration transaction carries two coordinated bundles: a
+//! [`BundleVersion::orchard_v3`] bundle that spends the wallet's Orchard notes and has no
+//! outputs, and a [`BundleVersion::ironwood_v3`] bundle that creates Ironwood notes and has
+//! no spends. The Orchard bundle's (positive) value balance funds the Ironwood bundle's
+//! (negative) value balance, and the difference between them is the transaction fee.
+//!
+//! Both bundles are [`BundleType::UNPADDED`]: the per-pool value balances already reveal
+//! the transfer, so padding would only increase the fee. To make the created notes harder
+//! to link to the migrated total, the migrated value can be split into notes of common
+//! denominations (see [`Split`]).
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{Builder, BundleType, SpendError};
+use crate::{
+    bundle::{BundleVersion, Flags},
+    keys::{FullViewingKey, Scope},
+    memo::Memo,
+    note::Note,
+    tree::{Anchor, MerklePath},
+    value::NoteValue,
+};
+
+/// How a [`Migrator`] divides the migrated value among the Ironwood notes it creates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Split {
+    /// The migrated value is sent to a single note.
+    Single,
+    /// The migrated value is split into notes whose values are 1, 2 or 5 times a power of
+    /// ten, using as few notes as possible for each decimal digit. For example, 370 is
+    /// split into notes of 200, 100, 50 and 20.
+    Denominations,
+}
+
+impl Split {
+    /// Returns the values of the notes that `value` is split into, largest first.
+    fn values(self, value: u64) -> Vec<u64> {
+        match self {
+            Split::Single => vec![value],
+            Split::Denominations => {
+                let mut values = vec![];
+                let mut remaining = value;
+                let mut unit = 1u64;
+                while remaining > 0 {
+                    let parts: &[u64] = match remaining % 10 {
+                        0 => &[],
+                        1 => &[1],
+                        2 => &[2],
+                        3 => &[2, 1],
+                        4 => &[2, 2],
+                        5 => &[5],
+                        6 => &[5, 1],
+                        7 => &[5, 2],
+                        8 => &[5, 2, 1],
+                        _ => &[5, 2, 2],
+                    };
+                    // Within a digit, parts are largest first; digits are visited
+                    // smallest first, so the whole list is reversed at the end.
+                    values.extend(parts.iter().rev().map(|part| part * unit));
+                    remaining /= 10;
+                    unit = unit.saturating_mul(10);
+                }
+                values.reverse();
+                values
+            }
+        }
+    }
+}
+
+/// An error type for the kinds of errors that can occur while planning a migration.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MigrationError {
+    /// The notes to be migrated do not cover the fee with a nonzero value left over.
+    InsufficientFunds {
+        /// The total value of the notes to be migrated.
+        available: u64,
+        /// The smallest total value from which the migration could create a note.
+        required: u64,
+    },
+    /// An overflow occurred while summing the values of the notes and the fee.
+    ValueOverflow,
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::InsufficientFunds {
+                available,
+                required,
+            } => write!(
+                f,
+                "Insufficient funds: {available} zatoshis available, {required} required"
+            ),
+            MigrationError::ValueOverflow => f.write_str("Overflow occurred during value planning"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MigrationError {}
+
+/// A helper that plans the migration of a wallet's Orchard notes to the Ironwood pool.
+///
+/// Every note must be owned by the migrator's full viewing key. The Ironwood notes are sent
+/// to that key's internal-scope address at diversifier index 0.
+#[derive(Debug)]
+pub struct Migrator {
+    orchard: Builder,
+    fvk: FullViewingKey,
+    split: Split,
+}
+
+impl Migrator {
+    /// Constructs a new migrator for Orchard notes owned by `fvk` that are witnessed at
+    /// `anchor`, splitting the migrated value as given by `split`.
+    pub fn new(anchor: Anchor, fvk: FullViewingKey, split: Split) -> Self {
+        let bundle_version = BundleVersion::orchard_v3();
+        Migrator {
+            orchard: Builder::new(
+                BundleType::UNPADDED,
+                bundle_version,
+                bundle_version.default_flags(),
+                anchor,
+            )
+            .expect("the default flags of a bundle version are representable under it"),
+            fvk,
+            split,
+        }
+    }
+
+    /// Adds an Orchard note to be migrated.
+    ///
+    /// Returns the errors of [`Builder::add_spend`].
+    pub fn add_note(&mut self, note: Note, merkle_path: MerklePath) -> Result<(), SpendError> {
+        self.orchard.add_spend(self.fvk.clone(), note, merkle_path)
+    }
+
+    /// Plans the migration of every added note.
+    ///
+    /// `fee_rule` is given the numbers of actions in the Orchard and Ironwood bundles, and
+    /// returns the fee for a transaction with those bundles. The fee is the difference
+    /// between the two bundles' value balances.
+    ///
+    /// With [`Split::Denominations`], the number of Ironwood notes depends on the value
+    /// left after the fee, which itself depends on the number of notes. The planner uses
+    /// the smallest number of Ironwood actions `n` for which the value left after the fee
+    /// for `n` actions splits into at most `n` notes; if it splits into fewer, the fee is
+    /// still the one for `n` actions.
+    pub fn plan(
+        self,
+        mut fee_rule: impl FnMut(usize, usize) -> NoteValue,
+    ) -> Result<Migration, MigrationError> {
+        let Migrator {
+            orchard,
+            fvk,
+            split,
+        } = self;
+
+        let available = orchard
+            .spends
+            .iter()
+            .try_fold(0u64, |acc, spend| {
+                acc.checked_add(spend.note.value().inner())
+            })
+            .ok_or(MigrationError::ValueOverflow)?;
+        let orchard_actions = orchard
+            .bundle_type
+            .num_actions(orchard.flags, orchard.spends.len(), 0)
+            .expect("the Orchard bundle has spends enabled");
+
+        let ironwood_version = BundleVersion::ironwood_v3();
+        let ironwood_flags = Flags::SPENDS_DISABLED;
+        let mut ironwood = Builder::new(
+            BundleType::UNPADDED,
+            ironwood_version,
+            ironwood_flags,
+            Anchor::empty_tree(),
+        )
+        .expect("output-only flags are representable under the Ironwood bundle version");
+
+        let mut ironwood_actions = 1;
+        let (fee, values) = loop {
+            let fee = fee_rule(orchard_actions, ironwood_actions);
+            let value = available
+                .checked_sub(fee.inner())
+                .filter(|value| *value > 0)
+                .ok_or_else(|| match fee.inner().checked_add(1) {
+                    Some(required) => MigrationError::InsufficientFunds {
+                        available,
+                        required,
+                    },
+                    None => MigrationError::ValueOverflow,
+                })?;
+            let values = split.values(value);
+            if values.len() <= ironwood_actions {
+                break (fee, values);
+            }
+            ironwood_actions += 1;
+        };
+
+        let recipient = fvk.address_at(0u32, Scope::Internal);
+        let ovk = fvk.to_ovk(Scope::Internal);
+        let outputs = values
+            .into_iter()
+            .map(NoteValue::from_raw)
+            .collect::<Vec<_>>();
+        for value in &outputs {
+            ironwood
+                .add_output(Some(ovk.clone()), recipient, *value, Memo::Empty)
+                .expect("the Ironwood bundle has outputs and cross-address transfers enabled");
+        }
+
+        Ok(Migration {
+            orchard,
+            ironwood,
+            fee,
+            outputs,
+        })
+    }
+}
+
+/// A planned migration, produced by [`Migrator::plan`].
+#[derive(Debug)]
+pub struct Migration {
+    orchard: Builder,
+    ironwood: Builder,
+    fee: NoteValue,
+    outputs: Vec<NoteValue>,
+}
+
+impl Migration {
+    /// Returns the transaction fee: the Orchard bundle's value balance plus the Ironwood
+    /// bundle's value balance.
+    pub fn fee(&self) -> NoteValue {
+        self.fee
+    }
+
+    /// Returns the values of the Ironwood notes to be created, largest first.
+    pub fn outputs(&self) -> &[NoteValue] {
+        &self.outputs
+    }
+
+    /// Returns the builders for the Orchard bundle (spends only) and the Ironwood bundle
+    /// (outputs only), which must be included in the same transaction.
+    pub fn into_builders(self) -> (Builder, Builder) {
+        (self.orchard, self.ironwood)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    #[cfg(feature = "circuit")]
+    use proptest::prelude::*;
+    #[cfg(feature = "circuit")]
+    use rand::rngs::OsRng;
+
+    use super::Split;
+    #[cfg(feature = "circuit")]
+    use super::{MigrationError, Migrator};
+    #[cfg(feature = "circuit")]
+    use crate::{
+        builder::testing::arb_shared_anchor_notes, bundle::BundleVersion, keys::FullViewingKey,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn denominations_split_each_digit() {
+        assert_eq!(Split::Single.values(370), vec![370]);
+        assert_eq!(Split::Denominations.values(370), vec![200, 100, 50, 20]);
+        assert_eq!(
+            Split::Denominations.values(9_084),
+            vec![5_000, 2_000, 2_000, 50, 20, 10, 2, 2]
+        );
+        assert_eq!(
+            Split::Denominations.values(u64::MAX).iter().sum::<u64>(),
+            u64::MAX
+        );
+    }
+
+    #[cfg(feature = "circuit")]
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(5))]
+
+        #[test]
+        fn migration_balances_the_two_bundles(
+            (sk, notes, anchor) in arb_shared_anchor_notes(
+                vec![NoteValue::from_raw(1_000_000), NoteValue::from_raw(234_567)],
+                BundleVersion::orchard_v3().note_version(),
+            ),
+        ) {
+            let fvk = FullViewingKey::from(&sk);
+            let migrator = |split| {
+                let mut migrator = Migrator::new(anchor, fvk.clone(), split);
+                for (note, path) in &notes {
+                    migrator.add_note(*note, path.clone()).unwrap();
+                }
+                migrator
+            };
+            let fee_rule = |orchard: usize, ironwood: usize| {
+                NoteValue::from_raw(1000 * (orchard + ironwood) as u64)
+            };
+
+            // A single output: 2 Orchard actions and 1 Ironwood action.
+            let migration = migrator(Split::Single).plan(fee_rule).unwrap();
+            prop_assert_eq!(migration.fee(), NoteValue::from_raw(3000));
+            prop_assert_eq!(migration.outputs(), &[NoteValue::from_raw(1_231_567)][..]);
+
+            // The value left after the fee for 9 Ironwood actions (1_223_567) splits into
+            // 10 notes, but the value left after the fee for 10 actions (1_222_567) splits
+            // into 9, so the fee is for 10 actions.
+            let migration = migrator(Split::Denominations).plan(fee_rule).unwrap();
+            prop_assert_eq!(migration.fee(), NoteValue::from_raw(12_000));
+            prop_assert_eq!(migration.outputs().len(), 9);
+            prop_assert_eq!(
+                migration.outputs().iter().map(|v| v.inner()).sum::<u64>(),
+                1_222_567
+            );
+
+            let (orchard, ironwood) = migration.into_builders();
+            prop_assert_eq!(orchard.value_balance::<i64>().unwrap(), 1_234_567);
+            prop_assert_eq!(ironwood.value_balance::<i64>().unwrap(), -1_222_567);
+            let (orchard, _) = orchard.build_for_pczt(OsRng).unwrap();
+            let (ironwood, _) = ironwood.build_for_pczt(OsRng).unwrap();
+            prop_assert_eq!(orchard.actions().len(), 2);
+            prop_assert_eq!(ironwood.actions().len(), 9);
+            prop_assert_eq!(*ironwood.bundle_version(), BundleVersion::ironwood_v3());
+
+            prop_assert_eq!(
+                migrator(Split::Single)
+                    .plan(|_, _| NoteValue::from_raw(1_234_567))
+                    .unwrap_err(),
+                MigrationError::InsufficientFunds {
+                    available: 1_234_567,
+                    required: 1_234_568,
+                }
+            );
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! Note selection and fee-aware planning of Orchard bundles.
+//!
+//! A [`Planner`] is given the notes a wallet could spend, the payments it wants to make,
+//! and a fee rule. [`Planner::plan`] selects enough notes to pay for the payments and the
+//! fee, adds a change output for any excess, and returns a [`Plan`] containing the
+//! populated [`Builder`].
+//!
+//! The fee depends on the number of actions in the bundle, which in turn depends on the
+//! [`BundleType`], the [`Flags`], and the numbers of spends and outputs (see
+//! [`BundleType::num_actions`]). The planner evaluates the fee rule for the exact action
+//! count of each candidate selection, so the resulting bundle's value balance is exactly
+//! the fee.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{BuildError, Builder, BundleType, OutputError, OutputInfo, SpendError, SpendInfo};
+use crate::{
+    bundle::{BundleVersion, Flags},
+    keys::{FullViewingKey, OutgoingViewingKey, Scope},
+    memo::Memo,
+    note::Note,
+    tree::{Anchor, MerklePath},
+    value::NoteValue,
+    Address,
+};
+
+/// An error type for the kinds of errors that can occur while planning a bundle.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PlanError {
+    /// The candidate notes do not cover the payments and the fee.
+    InsufficientFunds {
+        /// The total value of the candidate notes.
+        available: u64,
+        /// The value needed to make the payments and pay the fee when spending every
+        /// candidate note.
+        required: u64,
+    },
+    /// The numbers of spends and outputs are incompatible with the bundle type and flags.
+    BundleTypeNotSatisfiable(&'static str),
+    /// An overflow occurred while summing the values of the notes, payments, and fee.
+    ValueOverflow,
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::InsufficientFunds {
+                available,
+                required,
+            } => write!(
+                f,
+                "Insufficient funds: {available} zatoshis available, {required} required"
+            ),
+            PlanError::BundleTypeNotSatisfiable(e) => {
+                write!(
+                    f,
+                    "Bundle structure did not conform to requested bundle type: {e}"
+                )
+            }
+            PlanError::ValueOverflow => f.write_str("Overflow occurred during value planning"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlanError {}
+
+/// A planner that selects notes to spend for a set of payments, and constructs a
+/// [`Builder`] for the resulting bundle.
+///
+/// Every candidate note must be owned by the planner's full viewing key, which also
+/// receives the change (at its internal-scope address at diversifier index 0).
+#[derive(Debug)]
+pub struct Planner {
+    builder: Builder,
+    anchor: Anchor,
+    fvk: FullViewingKey,
+    candidates: Vec<SpendInfo>,
+    payments: Vec<OutputInfo>,
+}
+
+impl Planner {
+    /// Constructs a new planner for a bundle of the given type, version, flags, and anchor,
+    /// spending notes owned by `fvk` and returning change to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Builder::new`].
+    pub fn new(
+        bundle_type: BundleType,
+        bundle_version: BundleVersion,
+        flags: Flags,
+        anchor: Anchor,
+        fvk: FullViewingKey,
+    ) -> Result<Self, BuildError> {
+        Ok(Planner {
+            builder: Builder::new(bundle_type, bundle_version, flags, anchor)?,
+            anchor,
+            fvk,
+            candidates: vec![],
+            payments: vec![],
+        })
+    }
+
+    /// Adds a note that the planner may select to spend.
+    ///
+    /// Candidates are indexed from zero in the order they are added; these are the
+    /// indices reported by [`Plan::selected`].
+    ///
+    /// Returns an error if spends are disabled, if the planner's full viewing key does not
+    /// own `note`, or if `merkle_path` does not root to the planner's anchor.
+    pub fn add_candidate(&mut self, note: Note, merkle_path: MerklePath) -> Result<(), SpendError> {
+        if !self.builder.flags.spends_enabled() {
+            return Err(SpendError::SpendsDisabled);
+        }
+
+        let spend =
+            SpendInfo::new(self.fvk.clone(), note, merkle_path).ok_or(SpendError::FvkMismatch)?;
+        if !spend.has_matching_anchor(&self.anchor) {
+            return Err(SpendError::AnchorMismatch);
+        }
+
+        self.candidates.push(spend);
+
+        Ok(())
+    }
+
+    /// Adds a payment of `value` to `recipient`.
+    ///
+    /// Returns the errors of [`Builder::add_output`].
+    pub fn add_payment(
+        &mut self,
+        ovk: Option<OutgoingViewingKey>,
+        recipient: Address,
+        value: NoteValue,
+        memo: impl Into<[u8; 512]>,
+    ) -> Result<(), OutputError> {
+        if !self.builder.flags.outputs_enabled() {
+            return Err(OutputError::OutputsDisabled);
+        }
+        if !self.builder.flags.cross_address_enabled() {
+            return Err(OutputError::CrossAddressDisabled);
+        }
+
+        self.payments.push(OutputInfo::new(
+            ovk,
+            recipient,
+            value,
+            self.builder.note_version(),
+            memo,
+        ));
+
+        Ok(())
+    }
+
+    /// Whether a change output can be added to this planner's bundle.
+    fn change_allowed(&self) -> bool {
+        let flags = &self.builder.flags;
+        flags.outputs_enabled() && (flags.cross_address_enabled() || flags.spends_enabled())
+    }
+
+    /// Selects the notes to spend and plans the bundle.
+    ///
+    /// `fee_rule` is given a number of Orchard actions and returns the fee for a
+    /// transaction whose Orchard bundle has that many actions. The fee is paid from the
+    /// bundle's value balance.
+    ///
+    /// Notes are selected largest first (ties in the order they were added), adding notes
+    /// until their value covers the payments and the fee for the resulting action count.
+    /// The bundle always balances exactly: a selection whose value equals the payments plus
+    /// the fee is planned without change, and otherwise the excess over the payments and
+    /// the fee for the bundle *with* a change output is returned to the planner's full
+    /// viewing key as change. If that excess is zero, the change output is omitted.
+    ///
+    /// If no selection balances exactly or covers the fee for a change output, but some
+    /// selection covers the payments and the fee without change, the first such
+    /// selection is planned without change and its whole excess over the payments is paid
+    /// as the fee. In both cases [`Plan::fee`] exceeds the fee rule's fee for
+    /// [`Plan::num_actions`].
+    ///
+    /// Returns [`PlanError::InsufficientFunds`] if the candidate notes do not cover the
+    /// payments and the fee.
+    pub fn plan(self, mut fee_rule: impl FnMut(usize) -> NoteValue) -> Result<Plan, PlanError> {
+        let bundle_type = self.builder.bundle_type;
+        let flags = self.builder.flags;
+        let num_payments = self.payments.len();
+        let num_actions = |num_spends, num_outputs| {
+            bundle_type
+                .num_actions(flags, num_spends, num_outputs)
+                .map_err(PlanError::BundleTypeNotSatisfiable)
+        };
+
+        let paid = self
+            .payments
+            .iter()
+            .try_fold(0u64, |acc, payment| acc.checked_add(payment.value.inner()))
+            .ok_or(PlanError::ValueOverflow)?;
+
+        let mut order: Vec<usize> = (0..self.candidates.len()).collect();
+        order.sort_by_key(|&i| core::cmp::Reverse(self.candidates[i].note.value().inner()));
+
+        let mut available = 0u64;
+        let mut required = 0u64;
+        let mut excess = None;
+        for num_spends in 0..=order.len() {
+            if num_spends > 0 {
+                let value = self.candidates[order[num_spends - 1]].note.value().inner();
+                available = available
+                    .checked_add(value)
+                    .ok_or(PlanError::ValueOverflow)?;
+            }
+
+            // Without change, the selection must cover the payments and the fee exactly.
+            let actions = num_actions(num_spends, num_payments)?;
+            let fee = fee_rule(actions);
+            required = paid
+                .checked_add(fee.inner())
+                .ok_or(PlanError::ValueOverflow)?;
+            if available == required {
+                return Ok(self.into_plan(&order[..num_spends], fee, None, actions));
+            }
+
+            // With change, it must cover them and the fee for the additional output.
+            if available > required && self.change_allowed() {
+                let change_actions = num_actions(num_spends, num_payments + 1)?;
+                let change_fee = fee_rule(change_actions);
+                let change = paid
+                    .checked_add(change_fee.inner())
+                    .and_then(|required| available.checked_sub(required));
+                match change {
+                    // A zero-valued change output would only add an action, so the excess
+                    // over the fee without change is paid as part of the fee instead.
+                    Some(0) => {
+                        return Ok(self.into_plan(&order[..num_spends], change_fee, None, actions))
+                    }
+                    Some(change) => {
+                        return Ok(self.into_plan(
+                            &order[..num_spends],
+                            change_fee,
+                            Some(NoteValue::from_raw(change)),
+                            change_actions,
+                        ))
+                    }
+                    None => (),
+                }
+            }
+
+            if available > required && excess.is_none() {
+                excess = Some((num_spends, available - paid, actions));
+            }
+        }
+
+        // No selection balances exactly or can fund a change output, so the first one
+        // that covers the payments pays its excess over them as the fee.
+        if let Some((num_spends, fee, actions)) = excess {
+            return Ok(self.into_plan(
+                &order[..num_spends],
+                NoteValue::from_raw(fee),
+                None,
+                actions,
+            ));
+        }
+
+        Err(PlanError::InsufficientFunds {
+            available,
+            required,
+        })
+    }
+
+    /// Moves the selected candidates, the payments, and the change (if any) into the
+    /// builder.
+    fn into_plan(
+        self,
+        selected: &[usize],
+        fee: NoteValue,
+        change: Option<NoteValue>,
+        num_actions: usize,
+    ) -> Plan {
+        let Planner {
+            mut builder,
+            anchor: _,
+            fvk,
+            candidates,
+            payments,
+        } = self;
+
+        let mut selected = selected.to_vec();
+        selected.sort_unstable();
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        builder.spends.extend(selected.iter().map(|&i| {
+            candidates[i]
+                .take()
+                .expect("each candidate is selected once")
+        }));
+        builder.outputs.extend(payments);
+        if let Some(value) = change {
+            builder
+                .add_change_output(
+                    fvk.clone(),
+                    Some(fvk.to_ovk(Scope::Internal)),
+                    fvk.address_at(0u32, Scope::Internal),
+                    value,
+                    Memo::Empty,
+                )
+                .expect("change is only planned when it can be added");
+        }
+
+        Plan {
+            builder,
+            selected,
+            fee,
+            change,
+            num_actions,
+        }
+    }
+}
+
+/// A planned bundle, produced by [`Planner::plan`].
+#[derive(Debug)]
+pub struct Plan {
+    builder: Builder,
+    selected: Vec<usize>,
+    fee: NoteValue,
+    change: Option<NoteValue>,
+    num_actions: usize,
+}
+
+impl Plan {
+    /// Returns the indices (in the order they were added by [`Planner::add_candidate`]) of
+    /// the candidate notes selected to be spent, in ascending order.
+    ///
+    /// The selected notes are added to the builder in this order.
+    pub fn selected(&self) -> &[usize] {
+        &self.selected
+    }
+
+    /// Returns the fee that the bundle's value balance pays.
+    ///
+    /// This is the fee rule's fee for [`Self::num_actions`], plus any excess that could
+    /// not be returned as change (see [`Planner::plan`]).
+    pub fn fee(&self) -> NoteValue {
+        self.fee
+    }
+
+    /// Returns the value of the change output, or `None` if the plan has no change output.
+    pub fn change(&self) -> Option<NoteValue> {
+        self.change
+    }
+
+    /// Returns the number of actions that the builder will produce, as passed to the fee
+    /// rule.
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
+
+    /// Returns the populated builder.
+    pub fn into_builder(self) -> Builder {
+        self.builder
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use proptest::prelude::*;
+    use rand::rngs::OsRng;
+
+    use super::{PlanError, Planner};
+    use crate::{
+        builder::{testing::arb_shared_anchor_notes, BundleType, OutputError},
+        bundle::{BundleVersion, Flags},
+        keys::{FullViewingKey, Scope, SpendingKey},
+        value::NoteValue,
+    };
+
+    /// A ZIP 317-style fee rule: 5000 zatoshis per action, with a 2-action minimum.
+    fn zip317(num_actions: usize) -> NoteValue {
+        NoteValue::from_raw(5000 * core::cmp::max(num_actions, 2) as u64)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(5))]
+
+        #[test]
+        fn plan_selects_notes_and_computes_change(
+            (sk, notes, anchor) in arb_shared_anchor_notes(
+                vec![
+                    NoteValue::from_raw(10_000),
+                    NoteValue::from_raw(40_000),
+                    NoteValue::from_raw(25_000),
+                ],
+                BundleVersion::orchard_v2().note_version(),
+            ),
+        ) {
+            let fvk = FullViewingKey::from(&sk);
+            let recipient = FullViewingKey::from(&SpendingKey::from_bytes([7; 32]).unwrap())
+                .address_at(0u32, Scope::External);
+            let planner = |payment: u64| {
+                let bundle_version = BundleVersion::orchard_v2();
+                let mut planner = Planner::new(
+                    BundleType::DEFAULT,
+                    bundle_version,
+                    bundle_version.default_flags(),
+                    anchor,
+                    fvk.clone(),
+                )
+                .unwrap();
+                for (note, path) in &notes {
+                    planner.add_candidate(*note, path.clone()).unwrap();
+                }
+                planner
+                    .add_payment(None, recipient, NoteValue::from_raw(payment), [0; 512])
+                    .unwrap();
+                planner
+            };
+
+            // The largest note covers the payment and the fee of a 2-action bundle.
+            let plan = planner(20_000).plan(zip317).unwrap();
+            prop_assert_eq!(plan.selected(), &[1][..]);
+            prop_assert_eq!(plan.num_actions(), 2);
+            prop_assert_eq!(plan.fee(), NoteValue::from_raw(10_000));
+            prop_assert_eq!(plan.change(), Some(NoteValue::from_raw(10_000)));
+            let builder = plan.into_builder();
+            prop_assert_eq!(builder.value_balance::<i64>().unwrap(), 10_000);
+            let (bundle, _) = builder.build_for_pczt(OsRng).unwrap();
+            prop_assert_eq!(bundle.actions().len(), 2);
+
+            // An exact match needs no change output.
+            let plan = planner(30_000).plan(zip317).unwrap();
+            prop_assert_eq!(plan.selected(), &[1][..]);
+            prop_assert_eq!(plan.change(), None);
+
+            // A larger payment needs a second note; the change still fits in 2 actions.
+            let plan = planner(45_000).plan(zip317).unwrap();
+            prop_assert_eq!(plan.selected(), &[1, 2][..]);
+            prop_assert_eq!(plan.change(), Some(NoteValue::from_raw(10_000)));
+
+            // Spending all three notes costs 3 actions.
+            let plan = planner(58_000).plan(zip317).unwrap();
+            prop_assert_eq!(plan.selected(), &[0, 1, 2][..]);
+            prop_assert_eq!(plan.num_actions(), 3);
+            prop_assert_eq!(plan.change(), Some(NoteValue::from_raw(2_000)));
+
+            prop_assert_eq!(
+                planner(61_000).plan(zip317).unwrap_err(),
+                PlanError::InsufficientFunds {
+                    available: 75_000,
+                    required: 76_000,
+                }
+            );
+        }
+
+        #[test]
+        fn plan_counts_restricted_actions(
+            (sk, notes, anchor) in arb_shared_anchor_notes(
+                vec![NoteValue::from_raw(30_000), NoteValue::from_raw(30_000)],
+                BundleVersion::orchard_v3().note_version(),
+            ),
+        ) {
+            let fvk = FullViewingKey::from(&sk);
+            let bundle_version = BundleVersion::orchard_v3();
+            let mut planner = Planner::new(
+                BundleType::DEFAULT,
+                bundle_version,
+                Flags::CROSS_ADDRESS_DISABLED,
+                anchor,
+                fvk.clone(),
+            )
+            .unwrap();
+            for (note, path) in &notes {
+                planner.add_candidate(*note, path.clone()).unwrap();
+            }
+            prop_assert_eq!(
+                planner.add_payment(
+                    None,
+                    fvk.address_at(0u32, Scope::External),
+                    NoteValue::from_raw(1),
+                    [0; 512],
+                ),
+                Err(OutputError::CrossAddressDisabled)
+            );
+
+            // A fee of over 42_000 needs both notes. In a restricted bundle, each spend and
+            // the change output take their own action, so the fee is for 3 actions.
+            let plan = planner
+                .plan(|num_actions| NoteValue::from_raw(42_000 + 1000 * num_actions as u64))
+                .unwrap();
+            prop_assert_eq!(plan.selected(), &[0, 1][..]);
+            prop_assert_eq!(plan.num_actions(), 3);
+            prop_assert_eq!(plan.fee(), NoteValue::from_raw(45_000));
+            prop_assert_eq!(plan.change(), Some(NoteValue::from_raw(15_000)));
+            let (bundle, _) = plan.into_builder().build_for_pczt(OsRng).unwrap();
+            prop_assert_eq!(bundle.actions().len(), 3);
+        }
+
+        #[test]
+        fn plan_pays_excess_that_cannot_fund_change_as_fee(
+            (sk, notes, anchor) in arb_shared_anchor_notes(
+                vec![NoteValue::from_raw(21_500)],
+                BundleVersion::orchard_v2().note_version(),
+            ),
+        ) {
+            let fvk = FullViewingKey::from(&sk);
+            let recipient = FullViewingKey::from(&SpendingKey::from_bytes([7; 32]).unwrap())
+                .address_at(0u32, Scope::External);
+            // Two payments fit in 2 actions, but a change output would need a third.
+            let planner = |first_payment: u64| {
+                let bundle_version = BundleVersion::orchard_v2();
+                let mut planner = Planner::new(
+                    BundleType::DEFAULT,
+                    bundle_version,
+                    bundle_version.default_flags(),
+                    anchor,
+                    fvk.clone(),
+                )
+                .unwrap();
+                for (note, path) in &notes {
+                    planner.add_candidate(*note, path.clone()).unwrap();
+                }
+                for payment in [first_payment, 1_000] {
+                    planner
+                        .add_payment(None, recipient, NoteValue::from_raw(payment), [0; 512])
+                        .unwrap();
+                }
+                planner
+            };
+
+            // The note covers the payments and the 2-action fee of 10_000, but the excess
+            // of 1_500 does not cover the 5_000 more that a change output would cost.
+            let plan = planner(9_000).plan(zip317).unwrap();
+            prop_assert_eq!(plan.num_actions(), 2);
+            prop_assert_eq!(plan.fee(), NoteValue::from_raw(11_500));
+            prop_assert_eq!(plan.change(), None);
+            let builder = plan.into_builder();
+            prop_assert_eq!(builder.value_balance::<i64>().unwrap(), 11_500);
+            let (bundle, _) = builder.build_for_pczt(OsRng).unwrap();
+            prop_assert_eq!(bundle.actions().len(), 2);
+
+            // The excess exactly covers the cost of a change output, which would be
+            // zero-valued, so it is omitted and the excess is paid as the fee.
+            let plan = planner(5_500).plan(zip317).unwrap();
+            prop_assert_eq!(plan.num_actions(), 2);
+            prop_assert_eq!(plan.fee(), NoteValue::from_raw(15_000));
+            prop_assert_eq!(plan.change(), None);
+            let (bundle, _) = plan.into_builder().build_for_pczt(OsRng).unwrap();
+            prop_assert_eq!(bundle.actions().len(), 2);
+
+            // One zatoshi more funds a change output.
+            let plan = planner(5_499).plan(zip317).unwrap();
+            prop_assert_eq!(plan.num_actions(), 3);
+            prop_assert_eq!(plan.fee(), NoteValue::from_raw(15_000));
+            prop_assert_eq!(plan.change(), Some(NoteValue::from_raw(1)));
+
+            prop_assert_eq!(
+                planner(11_000).plan(zip317).unwrap_err(),
+                PlanError::InsufficientFunds {
+                    available: 21_500,
+                    required: 22_000,
+                }
+            );
+        }
+    }
+}
@@ -3,11 +3,21 @@
 use alloc::vec::Vec;
 
 pub mod commitments;
+mod disclosure;
+mod effects;
+pub(crate) mod encoding;
+mod sent;
+pub use disclosure::{
+    DisclosedPayment, DisclosureError, PaymentDisclosure, UnsignedPaymentDisclosure,
+    MAX_DISCLOSURE_MESSAGE_SIZE,
+};
+pub use effects::{AccountEffects, ReceivedNote, SpentNote};
+pub use sent::{SentOutput, SentOutputKind};
 
 #[cfg(feature = "circuit")]
 mod batch;
 #[cfg(feature = "circuit")]
-pub use batch::{BatchError, BatchValidator};
+pub use batch::{BatchError, BatchValidator, MultiVersionBatchValidator};
 
 use core::fmt;
 
@@ -23,6 +33,7 @@ use crate::{
     address::Address,
     bundle::commitments::{hash_bundle_auth_data, hash_bundle_txid_data},
     keys::{IncomingViewingKey, OutgoingViewingKey, PreparedIncomingViewingKey},
+    memo::MemoBytes,
     note::{Note, NoteVersion},
     note_encryption::BundleDomain,
     primitives::redpallas::{self, Binding, SpendAuth},
@@ -663,7 +674,7 @@ impl<T: Authorization, V> Bundle<T, V> {
     pub fn decrypt_outputs_with_keys(
         &self,
         keys: &[IncomingViewingKey],
-    ) -> Vec<(usize, IncomingViewingKey, Note, Address, [u8; 512])> {
+    ) -> Vec<(usize, IncomingViewingKey, Note, Address, MemoBytes)> {
         let prepared_keys: Vec<_> = keys
             .iter()
             .map(|ivk| (ivk, PreparedIncomingViewingKey::new(ivk)))
@@ -675,7 +686,7 @@ impl<T: Authorization, V> Bundle<T, V> {
                 let domain = BundleDomain::for_action(action, self.bundle_version.note_version());
                 prepared_keys.iter().find_map(|(ivk, prepared_ivk)| {
                     try_note_decryption(&domain, prepared_ivk, action)
-                        .map(|(n, a, m)| (idx, (*ivk).clone(), n, a, m))
+                        .map(|(n, a, m)| (idx, (*ivk).clone(), n, a, m.into()))
                 })
             })
             .collect()
@@ -688,11 +699,11 @@ impl<T: Authorization, V> Bundle<T, V> {
         &self,
         action_idx: usize,
         key: &IncomingViewingKey,
-    ) -> Option<(Note, Address, [u8; 512])> {
+    ) -> Option<(Note, Address, MemoBytes)> {
         let prepared_ivk = PreparedIncomingViewingKey::new(key);
         self.actions.get(action_idx).and_then(move |action| {
             let domain = BundleDomain::for_action(action, self.bundle_version.note_version());
-            try_note_decryption(&domain, &prepared_ivk, action)
+            try_note_decryption(&domain, &prepared_ivk, action).map(|(n, a, m)| (n, a, m.into()))
         })
     }
 
@@ -703,7 +714,7 @@ impl<T: Authorization, V> Bundle<T, V> {
     pub fn recover_outputs_with_ovks(
         &self,
         keys: &[OutgoingViewingKey],
-    ) -> Vec<(usize, OutgoingViewingKey, Note, Address, [u8; 512])> {
+    ) -> Vec<(usize, OutgoingViewingKey, Note, Address, MemoBytes)> {
         self.actions
             .iter()
             .enumerate()
@@ -717,7 +728,7 @@ impl<T: Authorization, V> Bundle<T, V> {
                         action.cv_net(),
                         &action.encrypted_note().out_ciphertext,
                     )
-                    .map(|(n, a, m)| (idx, key.clone(), n, a, m))
+                    .map(|(n, a, m)| (idx, key.clone(), n, a, m.into()))
                 })
             })
             .collect()
@@ -730,7 +741,7 @@ impl<T: Authorization, V> Bundle<T, V> {
         &self,
         action_idx: usize,
         key: &OutgoingViewingKey,
-    ) -> Option<(Note, Address, [u8; 512])> {
+    ) -> Option<(Note, Address, MemoBytes)> {
         self.actions.get(action_idx).and_then(move |action| {
             let domain = BundleDomain::for_action(action, self.bundle_version.note_version());
             try_output_recovery_with_ovk(
@@ -740,6 +751,7 @@ impl<T: Authorization, V> Bundle<T, V> {
                 action.cv_net(),
                 &action.encrypted_note().out_ciphertext,
             )
+            .map(|(n, a, m)| (n, a, m.into()))
         })
     }
 }
@@ -1280,6 +1292,38 @@ pub(crate) mod tests {
         )
     }
 
+    /// Returns a builder with a spend of a note of `fvk` to `recipient`, along with the
+    /// note's nullifier. The note has a dummy Merkle path, whose root is the anchor.
+    #[cfg(feature = "circuit")]
+    pub(crate) fn builder_with_spend(
+        mut rng: impl rand::RngCore,
+        fvk: &crate::keys::FullViewingKey,
+        recipient: crate::Address,
+        value: crate::value::NoteValue,
+        bundle_version: BundleVersion,
+        flags: Flags,
+    ) -> (crate::builder::Builder, crate::note::Nullifier) {
+        use crate::{
+            builder::{Builder, BundleType},
+            note::{Note, Nullifier, Rho},
+            tree::MerklePath,
+        };
+
+        let note = Note::new(
+            recipient,
+            value,
+            Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+            bundle_version.note_version(),
+            &mut rng,
+        );
+        let nf = note.nullifier(fvk);
+        let merkle_path = MerklePath::dummy(&mut rng);
+        let anchor = merkle_path.root(note.commitment().into());
+        let mut builder = Builder::new(BundleType::DEFAULT, bundle_version, flags, anchor).unwrap();
+        builder.add_spend(fvk.clone(), note, merkle_path).unwrap();
+        (builder, nf)
+    }
+
     #[cfg(feature = "circuit")]
     pub(crate) fn sample_authorized_bundle(
         n_actions: usize,
@@ -1,5 +1,5 @@
 use alloc::vec::Vec;
-use core::fmt;
+use core::{fmt, ops::Range};
 
 use halo2_proofs::plonk;
 use pasta_curves::vesta;
@@ -8,8 +8,9 @@ use tracing::debug;
 
 use super::{Authorized, Bundle};
 use crate::{
-    circuit::VerifyingKey,
+    circuit::{Instance, OrchardCircuitVersion, VerifyingKey},
     primitives::redpallas::{self, Binding, SpendAuth},
+    Proof,
 };
 
 /// A signature within an authorized Orchard bundle.
@@ -19,6 +20,15 @@ struct BundleSignature {
     signature: redpallas::batch::Item<SpendAuth, Binding>,
 }
 
+/// The proof of a bundle queued in a [`BatchValidator`], together with the public instances
+/// it is verified against and the range of the bundle's items in the signature queue.
+#[derive(Debug)]
+struct QueuedBundle {
+    proof: Proof,
+    instances: Vec<Instance>,
+    signatures: Range<usize>,
+}
+
 /// Error returned by [`BatchValidator::add_bundle`] when a bundle's flags require a circuit
 /// capability the validator's verifying key does not provide.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -30,6 +40,11 @@ pub enum BatchError {
     ///
     /// [`OrchardCircuitVersion::supports_cross_address_restriction`]: crate::circuit::OrchardCircuitVersion::supports_cross_address_restriction
     RestrictionUnsupportedByKey,
+    /// A [`MultiVersionBatchValidator`] has no verifying key for the bundle's circuit version
+    /// (see [`BundleVersion::circuit_version`]).
+    ///
+    /// [`BundleVersion::circuit_version`]: crate::bundle::BundleVersion::circuit_version
+    MissingVerifyingKey(OrchardCircuitVersion),
 }
 
 impl fmt::Display for BatchError {
@@ -40,6 +55,11 @@ impl fmt::Display for BatchError {
                 "bundle disables cross-address transfers, but the verifying key's circuit \
                  version does not constrain the cross-address restriction",
             ),
+            BatchError::MissingVerifyingKey(circuit_version) => write!(
+                f,
+                "no verifying key was provided for the bundle's circuit version ({:?})",
+                circuit_version,
+            ),
         }
     }
 }
@@ -54,7 +74,7 @@ impl core::error::Error for BatchError {}
 /// cannot enforce.
 #[derive(Debug)]
 pub struct BatchValidator<'a> {
-    proofs: plonk::BatchVerifier<vesta::Affine>,
+    bundles: Vec<QueuedBundle>,
     signatures: Vec<BundleSignature>,
     /// The verifying key every queued bundle is validated against, and which
     /// [`Self::validate`] finalizes the proof batch with.
@@ -65,7 +85,7 @@ impl<'a> BatchValidator<'a> {
     /// Constructs a new batch validation context that validates against `vk`.
     pub fn new(vk: &'a VerifyingKey) -> Self {
         BatchValidator {
-            proofs: plonk::BatchVerifier::new(),
+            bundles: vec![],
             signatures: vec![],
             vk,
         }
@@ -76,36 +96,56 @@ impl<'a> BatchValidator<'a> {
     /// Returns [`BatchError::RestrictionUnsupportedByKey`] if the bundle disables cross-address
     /// transfers but the validator's verifying key's circuit version does not support the
     /// cross-address restriction; in that case the bundle is not added to the batch.
+    ///
+    /// Bundles that are added are indexed from zero in the order they are added; these are
+    /// the indices reported by [`Self::validate_with_culprits`].
     pub fn add_bundle<V: Copy + Into<i64>>(
         &mut self,
         bundle: &Bundle<Authorized, V>,
         sighash: [u8; 32],
     ) -> Result<(), BatchError> {
-        if !bundle.flags().cross_address_enabled() && !self.vk.supports_cross_address_restriction()
-        {
-            return Err(BatchError::RestrictionUnsupportedByKey);
-        }
-
-        for action in bundle.actions().iter() {
-            self.signatures.push(BundleSignature {
-                signature: action
-                    .rk()
-                    .create_batch_item(action.authorization().clone(), &sighash),
-            });
-        }
+        let bundle = queue_bundle(&mut self.signatures, self.vk, bundle, sighash)?;
+        self.bundles.push(bundle);
+        Ok(())
+    }
 
-        self.signatures.push(BundleSignature {
-            signature: bundle
-                .binding_validating_key()
-                .create_batch_item(bundle.authorization().binding_signature().clone(), &sighash),
-        });
+    /// Batch-validates the RedPallas signatures of the queued bundles in `bundles`.
+    fn validate_signatures<R: RngCore + CryptoRng>(
+        &self,
+        bundles: &[QueuedBundle],
+        rng: R,
+    ) -> bool {
+        validate_signatures(
+            bundles
+                .iter()
+                .flat_map(|bundle| &self.signatures[bundle.signatures.clone()]),
+            rng,
+        )
+    }
 
-        bundle
-            .authorization()
-            .proof()
-            .add_to_batch(&mut self.proofs, bundle.to_instances());
+    /// Batch-validates the proofs of the queued bundles in `bundles`.
+    fn validate_proofs(&self, bundles: &[QueuedBundle]) -> bool {
+        validate_proofs(self.vk, bundles)
+    }
 
-        Ok(())
+    /// Appends to `culprits` the index (offset by `offset`) of every bundle in `bundles` that
+    /// fails `check`, by recursively bisecting the bundles that fail it as a batch.
+    fn bisect(
+        bundles: &[QueuedBundle],
+        offset: usize,
+        check: &mut impl FnMut(&[QueuedBundle]) -> bool,
+        culprits: &mut Vec<usize>,
+    ) {
+        if check(bundles) {
+            return;
+        }
+        if bundles.len() == 1 {
+            culprits.push(offset);
+            return;
+        }
+        let mid = bundles.len() / 2;
+        Self::bisect(&bundles[..mid], offset, check, culprits);
+        Self::bisect(&bundles[mid..], offset + mid, check, culprits);
     }
 
     /// Batch-validates the accumulated bundles.
@@ -113,8 +153,7 @@ impl<'a> BatchValidator<'a> {
     /// Returns `true` if every proof and signature in every bundle added to the batch
     /// validator is valid. Returns `false` if one or more proofs or signatures are
     /// invalid. No attempt is made to figure out which of the accumulated bundles might
-    /// be invalid; if that information is desired, construct separate [`BatchValidator`]s
-    /// for sub-batches of the bundles.
+    /// be invalid; if that information is desired, use [`Self::validate_with_culprits`].
     ///
     /// The cross-address-restriction capability is enforced when bundles are added (see
     /// [`Self::add_bundle`]), so it is already guaranteed here.
@@ -128,19 +167,201 @@ impl<'a> BatchValidator<'a> {
             return true;
         }
 
-        let mut validator = redpallas::batch::Verifier::new();
-        for sig in self.signatures.iter() {
-            validator.queue(sig.signature.clone());
+        // If signatures are valid, check the proofs.
+        self.validate_signatures(&self.bundles, rng) && self.validate_proofs(&self.bundles)
+    }
+
+    /// Batch-validates the accumulated bundles, identifying the invalid ones on failure.
+    ///
+    /// Returns `Ok(())` if every proof and signature in every bundle added to the batch
+    /// validator is valid, exactly when [`Self::validate`] would return `true`. Otherwise,
+    /// returns the indices (in the order the bundles were added by [`Self::add_bundle`]) of
+    /// every bundle with an invalid proof or signature, in ascending order.
+    ///
+    /// The signatures and proofs are bisected separately: each half of a failing batch is
+    /// re-validated as a sub-batch, reusing the queued signature items and proof instances,
+    /// until every invalid bundle is isolated. With `k` invalid bundles out of `n`, this
+    /// costs `O(k log n)` sub-batch validations on top of the full batch.
+    pub fn validate_with_culprits<R: RngCore + CryptoRng>(
+        self,
+        mut rng: R,
+    ) -> Result<(), Vec<usize>> {
+        if self.signatures.is_empty() {
+            return Ok(());
+        }
+
+        let mut culprits = vec![];
+        Self::bisect(
+            &self.bundles,
+            0,
+            &mut |bundles| self.validate_signatures(bundles, &mut rng),
+            &mut culprits,
+        );
+        Self::bisect(
+            &self.bundles,
+            0,
+            &mut |bundles| self.validate_proofs(bundles),
+            &mut culprits,
+        );
+
+        if culprits.is_empty() {
+            Ok(())
+        } else {
+            culprits.sort_unstable();
+            culprits.dedup();
+            Err(culprits)
+        }
+    }
+}
+
+/// Checks that `vk` can validate `bundle`, and queues the bundle's RedPallas signatures in
+/// `signatures`, returning the bundle's queued proof.
+fn queue_bundle<V: Copy + Into<i64>>(
+    signatures: &mut Vec<BundleSignature>,
+    vk: &VerifyingKey,
+    bundle: &Bundle<Authorized, V>,
+    sighash: [u8; 32],
+) -> Result<QueuedBundle, BatchError> {
+    if !bundle.flags().cross_address_enabled() && !vk.supports_cross_address_restriction() {
+        return Err(BatchError::RestrictionUnsupportedByKey);
+    }
+
+    let signatures_start = signatures.len();
+    for action in bundle.actions().iter() {
+        signatures.push(BundleSignature {
+            signature: action
+                .rk()
+                .create_batch_item(action.authorization().clone(), &sighash),
+        });
+    }
+
+    signatures.push(BundleSignature {
+        signature: bundle
+            .binding_validating_key()
+            .create_batch_item(bundle.authorization().binding_signature().clone(), &sighash),
+    });
+
+    Ok(QueuedBundle {
+        proof: bundle.authorization().proof().clone(),
+        instances: bundle.to_instances(),
+        signatures: signatures_start..signatures.len(),
+    })
+}
+
+/// Batch-validates `signatures` as a single RedPallas batch.
+fn validate_signatures<'s, R: RngCore + CryptoRng>(
+    signatures: impl IntoIterator<Item = &'s BundleSignature>,
+    rng: R,
+) -> bool {
+    let mut validator = redpallas::batch::Verifier::new();
+    for sig in signatures {
+        validator.queue(sig.signature.clone());
+    }
+
+    match validator.verify(rng) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("RedPallas batch validation failed: {}", e);
+            false
         }
+    }
+}
+
+/// Batch-validates the proofs of the queued bundles in `bundles` against `vk`.
+fn validate_proofs<'b>(
+    vk: &VerifyingKey,
+    bundles: impl IntoIterator<Item = &'b QueuedBundle>,
+) -> bool {
+    let mut proofs = plonk::BatchVerifier::<vesta::Affine>::new();
+    for bundle in bundles {
+        bundle
+            .proof
+            .add_to_batch(&mut proofs, bundle.instances.clone());
+    }
+    proofs.finalize(&vk.params, &vk.vk)
+}
+
+/// Batch validation context for Orchard bundles of several circuit versions.
+///
+/// This holds one proof batch per verifying key, and routes each bundle added with
+/// [`Self::add_bundle`] to the batch whose key matches the bundle's
+/// [`BundleVersion::circuit_version`]. The RedPallas signatures of every bundle, whatever
+/// its circuit version, are validated as one shared batch.
+///
+/// [`BundleVersion::circuit_version`]: crate::bundle::BundleVersion::circuit_version
+#[derive(Debug)]
+pub struct MultiVersionBatchValidator<'a> {
+    /// A verifying key for each supported circuit version, with the bundles queued against
+    /// it.
+    batches: Vec<(&'a VerifyingKey, Vec<QueuedBundle>)>,
+    signatures: Vec<BundleSignature>,
+}
 
-        match validator.verify(rng) {
-            // If signatures are valid, check the proofs.
-            Ok(()) => self.proofs.finalize(&self.vk.params, &self.vk.vk),
-            Err(e) => {
-                debug!("RedPallas batch validation failed: {}", e);
-                false
+impl<'a> MultiVersionBatchValidator<'a> {
+    /// Constructs a new batch validation context that validates each bundle against the
+    /// key in `vks` for its circuit version.
+    ///
+    /// If `vks` contains several keys for the same circuit version, the first is used.
+    pub fn new(vks: impl IntoIterator<Item = &'a VerifyingKey>) -> Self {
+        let mut batches: Vec<(&'a VerifyingKey, Vec<QueuedBundle>)> = vec![];
+        for vk in vks {
+            if batches
+                .iter()
+                .all(|(known, _)| known.circuit_version() != vk.circuit_version())
+            {
+                batches.push((vk, vec![]));
             }
         }
+        MultiVersionBatchValidator {
+            batches,
+            signatures: vec![],
+        }
+    }
+
+    /// Adds the proof and RedPallas signatures from the given bundle to the validator.
+    ///
+    /// Returns [`BatchError::MissingVerifyingKey`] if the validator has no verifying key for
+    /// the bundle's circuit version, and [`BatchError::RestrictionUnsupportedByKey`] if the
+    /// bundle disables cross-address transfers but that key's circuit version does not
+    /// support the cross-address restriction. In either case the bundle is not added to the
+    /// batch.
+    pub fn add_bundle<V: Copy + Into<i64>>(
+        &mut self,
+        bundle: &Bundle<Authorized, V>,
+        sighash: [u8; 32],
+    ) -> Result<(), BatchError> {
+        let circuit_version = bundle.bundle_version().circuit_version();
+        let (vk, bundles) = self
+            .batches
+            .iter_mut()
+            .find(|(vk, _)| vk.circuit_version() == circuit_version)
+            .ok_or(BatchError::MissingVerifyingKey(circuit_version))?;
+
+        let bundle = queue_bundle(&mut self.signatures, vk, bundle, sighash)?;
+        bundles.push(bundle);
+        Ok(())
+    }
+
+    /// Batch-validates the accumulated bundles.
+    ///
+    /// Returns `true` if every proof and signature in every bundle added to the batch
+    /// validator is valid. Returns `false` if one or more proofs or signatures are
+    /// invalid.
+    ///
+    /// The signatures are validated first, as a single batch. If they are valid, the proofs
+    /// are then validated as one batch per circuit version.
+    pub fn validate<R: RngCore + CryptoRng>(self, rng: R) -> bool {
+        if self.signatures.is_empty() {
+            // An empty batch is always valid, but is not free to run; skip it.
+            return true;
+        }
+
+        validate_signatures(&self.signatures, rng)
+            && self
+                .batches
+                .iter()
+                .filter(|(_, bundles)| !bundles.is_empty())
+                .all(|(vk, bundles)| validate_proofs(vk, bundles))
     }
 }
 
@@ -148,7 +369,7 @@ impl<'a> BatchValidator<'a> {
 mod tests {
     use rand::rngs::OsRng;
 
-    use super::{BatchError, BatchValidator};
+    use super::{BatchError, BatchValidator, MultiVersionBatchValidator};
     use crate::{
         bundle::tests::{sample_authorized_bundle, with_cross_address_disabled},
         circuit::{OrchardCircuitVersion, VerifyingKey},
@@ -180,6 +401,33 @@ mod tests {
         assert_eq!(validator.add_bundle(&bundle, [0; 32]), Ok(()));
     }
 
+    #[test]
+    fn multi_version_add_bundle_routes_by_circuit_version() {
+        let bundle = with_cross_address_disabled(sample_authorized_bundle(1))
+            .try_map_value_balance(i64::try_from)
+            .expect("generated bundle value balance fits in i64");
+        let circuit_version = bundle.bundle_version().circuit_version();
+
+        // Without a key for the bundle's circuit version, the bundle cannot be routed.
+        let mut validator = MultiVersionBatchValidator::new([]);
+        assert_eq!(
+            validator.add_bundle(&bundle, [0; 32]),
+            Err(BatchError::MissingVerifyingKey(circuit_version))
+        );
+
+        // With one, the bundle is checked against that key's capabilities.
+        let vk = VerifyingKey::build(circuit_version);
+        let mut validator = MultiVersionBatchValidator::new([&vk]);
+        assert_eq!(
+            validator.add_bundle(&bundle, [0; 32]),
+            if circuit_version.supports_cross_address_restriction() {
+                Ok(())
+            } else {
+                Err(BatchError::RestrictionUnsupportedByKey)
+            }
+        );
+    }
+
     #[test]
     fn empty_batch_validates() {
         for circuit_version in [
@@ -189,6 +437,7 @@ mod tests {
         ] {
             let vk = VerifyingKey::build(circuit_version);
             assert!(BatchValidator::new(&vk).validate(OsRng));
+            assert!(MultiVersionBatchValidator::new([&vk]).validate(OsRng));
         }
     }
 }
@@ -0,0 +1,586 @@
+//! Payment disclosures for Orchard outputs.
+//!
+//! The disclosure format is specific to this crate. It is not the format of the draft
+//! [ZIP 311], and disclosures are not interoperable with other implementations.
+//!
+//! A payment disclosure proves to a third party that an action of a transaction paid a
+//! given address, without revealing any viewing key. It reveals the output's recipient
+//! and ephemeral secret key `esk`, with which the verifier decrypts the action's
+//! `enc_ciphertext` and checks the recovered note against the action's `cmx` and `epk`.
+//!
+//! The disclosure is signed with the randomized spend authorizing key of one of the
+//! transaction's actions, showing that it was made by a party that authorized the
+//! transaction. The signature covers the transaction ID and an optional message, which
+//! a verifier can use to bind the disclosure to a challenge of its choosing.
+//!
+//! [ZIP 311]: https://zips.z.cash/zip-0311
+
+use alloc::{string::String, vec};
+use core::fmt;
+
+use blake2b_simd::Params;
+use corez::io::{self, Read, Write};
+use getset::Getters;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use zcash_note_encryption::{
+    try_output_recovery_with_ock, try_output_recovery_with_pkd_esk, OutgoingCipherKey,
+};
+
+use super::{
+    encoding::{read_array, read_compact_size, write_compact_size},
+    Authorization, Bundle,
+};
+use crate::{
+    address::Address,
+    keys::{EphemeralSecretKey, SpendAuthorizingKey},
+    memo::MemoBytes,
+    note::Note,
+    note_encryption::BundleDomain,
+    primitives::redpallas::{self, SpendAuth},
+    value::NoteValue,
+};
+
+const PAYMENT_DISCLOSURE_PERSONALIZATION: &[u8; 16] = b"ZcashOrchardPDis";
+
+/// The maximum length in bytes of a payment disclosure's message.
+pub const MAX_DISCLOSURE_MESSAGE_SIZE: usize = 0xFFFF;
+
+/// An error type for the kinds of errors that can occur while creating or verifying a
+/// [`PaymentDisclosure`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DisclosureError {
+    /// The bundle has no action with the given index.
+    InvalidActionIndex(usize),
+    /// The disclosed recipient and ephemeral secret key do not decrypt the action's output
+    /// to a note that matches the action's `cmx` and `epk`.
+    InvalidOutput,
+    /// The message is longer than [`MAX_DISCLOSURE_MESSAGE_SIZE`] bytes.
+    MessageTooLong(usize),
+    /// The spend authorizing key and randomizer do not derive the signing action's `rk`.
+    WrongSpendAuthorizingKey,
+    /// The signature is not valid under the signing action's `rk`.
+    InvalidSignature,
+    /// The disclosure names a different transaction from the one being verified.
+    TxidMismatch,
+}
+
+impl fmt::Display for DisclosureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisclosureError::InvalidActionIndex(idx) => {
+                write!(f, "Bundle has no action at index {idx}")
+            }
+            DisclosureError::InvalidOutput => {
+                write!(f, "Disclosed output does not match the action")
+            }
+            DisclosureError::MessageTooLong(len) => write!(
+                f,
+                "Disclosure message is {len} bytes long, but at most {MAX_DISCLOSURE_MESSAGE_SIZE} bytes are allowed"
+            ),
+            DisclosureError::WrongSpendAuthorizingKey => {
+                write!(f, "Spend authorizing key does not match the signing action")
+            }
+            DisclosureError::InvalidSignature => write!(f, "Disclosure signature is invalid"),
+            DisclosureError::TxidMismatch => {
+                write!(f, "Disclosure is for a different transaction")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DisclosureError {}
+
+/// Decrypts the output of `action_idx` with the given recipient and ephemeral secret key,
+/// checking it against the action's `cmx` and `epk`.
+fn open_output<T: Authorization, V>(
+    bundle: &Bundle<T, V>,
+    action_idx: usize,
+    recipient: Address,
+    esk: EphemeralSecretKey,
+) -> Result<(Note, MemoBytes), DisclosureError> {
+    let action = bundle
+        .actions()
+        .get(action_idx)
+        .ok_or(DisclosureError::InvalidActionIndex(action_idx))?;
+    let domain = BundleDomain::for_action(action, bundle.bundle_version().note_version());
+    match try_output_recovery_with_pkd_esk(&domain, *recipient.pk_d(), esk, action) {
+        Some((note, address, memo)) if address == recipient => Ok((note, memo.into())),
+        _ => Err(DisclosureError::InvalidOutput),
+    }
+}
+
+/// A payment disclosure for one action of a bundle, before it has been signed.
+///
+/// This is created by [`Bundle::disclose_output`] or
+/// [`Bundle::disclose_output_with_ock`].
+#[derive(Debug)]
+pub struct UnsignedPaymentDisclosure<'a, T: Authorization, V> {
+    bundle: &'a Bundle<T, V>,
+    action_idx: usize,
+    recipient: Address,
+    esk: [u8; 32],
+    message: String,
+}
+
+impl<T: Authorization, V> UnsignedPaymentDisclosure<'_, T, V> {
+    /// Sets the message covered by the disclosure's signature.
+    ///
+    /// Returns [`DisclosureError::MessageTooLong`] if `message` is longer than
+    /// [`MAX_DISCLOSURE_MESSAGE_SIZE`] bytes.
+    pub fn with_message(mut self, message: &str) -> Result<Self, DisclosureError> {
+        if message.len() > MAX_DISCLOSURE_MESSAGE_SIZE {
+            return Err(DisclosureError::MessageTooLong(message.len()));
+        }
+        self.message = message.into();
+        Ok(self)
+    }
+
+    /// Signs the disclosure for the transaction with the given ID.
+    ///
+    /// The signing key is `ask` randomized by `alpha`, which must derive the `rk` of the
+    /// action at `spend_action_idx`. `alpha` is the randomizer used to sign that action's
+    /// spend, such as [`SigningParts::alpha`] or a PCZT spend's `alpha`.
+    ///
+    /// `txid` must be the ID of the transaction that contains the bundle.
+    ///
+    /// [`SigningParts::alpha`]: crate::builder::SigningParts::alpha
+    pub fn sign<R: RngCore + CryptoRng>(
+        self,
+        rng: R,
+        txid: [u8; 32],
+        spend_action_idx: usize,
+        ask: &SpendAuthorizingKey,
+        alpha: &pallas::Scalar,
+    ) -> Result<PaymentDisclosure, DisclosureError> {
+        let rk = self
+            .bundle
+            .actions()
+            .get(spend_action_idx)
+            .ok_or(DisclosureError::InvalidActionIndex(spend_action_idx))?
+            .rk();
+        let rsk = ask.randomize(alpha);
+        if redpallas::VerificationKey::from(&rsk) != *rk {
+            return Err(DisclosureError::WrongSpendAuthorizingKey);
+        }
+
+        let mut disclosure = PaymentDisclosure {
+            txid,
+            action_idx: self.action_idx,
+            recipient: self.recipient,
+            esk: self.esk,
+            message: self.message,
+            spend_action_idx,
+            spend_auth_sig: [0; 64].into(),
+        };
+        disclosure.spend_auth_sig = rsk.sign(rng, disclosure.digest().as_bytes());
+        Ok(disclosure)
+    }
+}
+
+/// A signed payment disclosure for one action of a transaction's Orchard bundle.
+///
+/// A disclosure names the transaction by its ID, but it cannot check that a bundle
+/// belongs to that transaction. [`PaymentDisclosure::verify`] therefore takes the ID
+/// that the verifier computed for the transaction containing the bundle, and rejects
+/// the disclosure if it names a different transaction.
+#[derive(Clone, Debug, Getters)]
+pub struct PaymentDisclosure {
+    /// The ID of the transaction that contains the disclosed action.
+    #[getset(get = "pub")]
+    txid: [u8; 32],
+    /// The index of the disclosed action within the bundle.
+    #[getset(get = "pub")]
+    action_idx: usize,
+    /// The recipient of the disclosed output.
+    #[getset(get = "pub")]
+    recipient: Address,
+    esk: [u8; 32],
+    /// The message covered by the signature.
+    #[getset(get = "pub")]
+    message: String,
+    /// The index of the action whose `rk` verifies the signature.
+    #[getset(get = "pub")]
+    spend_action_idx: usize,
+    spend_auth_sig: redpallas::Signature<SpendAuth>,
+}
+
+impl PaymentDisclosure {
+    /// Computes the digest that the disclosure's signature covers.
+    fn digest(&self) -> blake2b_simd::Hash {
+        let mut h = Params::new()
+            .hash_length(32)
+            .personal(PAYMENT_DISCLOSURE_PERSONALIZATION)
+            .to_state();
+        h.update(&self.txid);
+        h.update(&(self.action_idx as u32).to_le_bytes());
+        h.update(&self.recipient.to_raw_address_bytes());
+        h.update(&self.esk);
+        h.update(&(self.message.len() as u32).to_le_bytes());
+        h.update(self.message.as_bytes());
+        h.update(&(self.spend_action_idx as u32).to_le_bytes());
+        h.finalize()
+    }
+
+    /// Verifies this disclosure against the Orchard bundle of the transaction with ID
+    /// `txid`, and returns the disclosed payment.
+    ///
+    /// `txid` must be computed by the caller from the transaction that contains
+    /// `bundle`; the disclosure's own [`PaymentDisclosure::txid`] is only a claim.
+    /// Returns [`DisclosureError::TxidMismatch`] if the disclosure names a different
+    /// transaction.
+    ///
+    /// This then checks the signature under the `rk` of the signing action, decrypts the
+    /// disclosed action's `enc_ciphertext` with the disclosed recipient and ephemeral
+    /// secret key, and checks the recovered note against the action's `cmx` and `epk`.
+    /// The caller is responsible for checking that the message is the one it expects.
+    pub fn verify<T: Authorization, V>(
+        &self,
+        txid: &[u8; 32],
+        bundle: &Bundle<T, V>,
+    ) -> Result<DisclosedPayment, DisclosureError> {
+        if txid != &self.txid {
+            return Err(DisclosureError::TxidMismatch);
+        }
+
+        let rk = bundle
+            .actions()
+            .get(self.spend_action_idx)
+            .ok_or(DisclosureError::InvalidActionIndex(self.spend_action_idx))?
+            .rk();
+        rk.verify(self.digest().as_bytes(), &self.spend_auth_sig)
+            .map_err(|_| DisclosureError::InvalidSignature)?;
+
+        let esk = Option::from(EphemeralSecretKey::from_bytes(&self.esk))
+            .ok_or(DisclosureError::InvalidOutput)?;
+        let (note, memo) = open_output(bundle, self.action_idx, self.recipient, esk)?;
+        Ok(DisclosedPayment {
+            recipient: self.recipient,
+            value: note.value(),
+            memo,
+        })
+    }
+
+    /// Writes this disclosure.
+    ///
+    /// The encoding is the transaction ID, the 32-bit little-endian index of the disclosed
+    /// action, the raw recipient address, the ephemeral secret key, the compactSize-prefixed
+    /// UTF-8 message, the 32-bit little-endian index of the signing action, and the
+    /// signature.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let index = |idx: usize| {
+            u32::try_from(idx)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "action index too large"))
+        };
+        writer.write_all(&self.txid)?;
+        writer.write_all(&index(self.action_idx)?.to_le_bytes())?;
+        writer.write_all(&self.recipient.to_raw_address_bytes())?;
+        writer.write_all(&self.esk)?;
+        write_compact_size(&mut writer, self.message.len())?;
+        writer.write_all(self.message.as_bytes())?;
+        writer.write_all(&index(self.spend_action_idx)?.to_le_bytes())?;
+        writer.write_all(&<[u8; 64]>::from(&self.spend_auth_sig))
+    }
+
+    /// Reads a disclosure written by [`PaymentDisclosure::write`].
+    ///
+    /// This only parses the disclosure; use [`PaymentDisclosure::verify`] to check it.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid_data = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let txid = read_array(&mut reader)?;
+        let action_idx = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+        let recipient = Option::from(Address::from_raw_address_bytes(&read_array(&mut reader)?))
+            .ok_or_else(|| invalid_data("invalid Orchard recipient address"))?;
+        let esk = read_array(&mut reader)?;
+        let message_len = read_compact_size(&mut reader)?;
+        if message_len > MAX_DISCLOSURE_MESSAGE_SIZE {
+            return Err(invalid_data("disclosure message too long"));
+        }
+        let mut message = vec![0; message_len];
+        reader.read_exact(&mut message)?;
+        let message =
+            String::from_utf8(message).map_err(|_| invalid_data("invalid UTF-8 message"))?;
+        let spend_action_idx = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+        let spend_auth_sig = redpallas::Signature::from(read_array::<_, 64>(&mut reader)?);
+
+        Ok(PaymentDisclosure {
+            txid,
+            action_idx,
+            recipient,
+            esk,
+            message,
+            spend_action_idx,
+            spend_auth_sig,
+        })
+    }
+}
+
+/// A payment verified by [`PaymentDisclosure::verify`].
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct DisclosedPayment {
+    /// The recipient of the payment.
+    recipient: Address,
+    /// The value of the payment.
+    value: NoteValue,
+    /// The memo field of the payment.
+    memo: MemoBytes,
+}
+
+impl<T: Authorization, V> Bundle<T, V> {
+    /// Prepares a payment disclosure for the output of the action at `action_idx`, given
+    /// the note that it created.
+    ///
+    /// The note's ephemeral secret key is derived from its `rseed`. Returns
+    /// [`DisclosureError::InvalidOutput`] if `note` is not the output of the action.
+    pub fn disclose_output(
+        &self,
+        action_idx: usize,
+        note: &Note,
+    ) -> Result<UnsignedPaymentDisclosure<'_, T, V>, DisclosureError> {
+        let esk = note.esk();
+        let esk_bytes = esk.to_bytes();
+        let (decrypted, _) = open_output(self, action_idx, note.recipient(), esk)?;
+        if decrypted.value() != note.value() || decrypted.rho() != note.rho() {
+            return Err(DisclosureError::InvalidOutput);
+        }
+
+        Ok(UnsignedPaymentDisclosure {
+            bundle: self,
+            action_idx,
+            recipient: note.recipient(),
+            esk: esk_bytes,
+            message: String::new(),
+        })
+    }
+
+    /// Prepares a payment disclosure for the output of the action at `action_idx`, given
+    /// the outgoing cipher key `ock` with which its `out_ciphertext` was encrypted.
+    ///
+    /// Returns [`DisclosureError::InvalidOutput`] if `ock` does not decrypt the action's
+    /// output.
+    pub fn disclose_output_with_ock(
+        &self,
+        action_idx: usize,
+        ock: &OutgoingCipherKey,
+    ) -> Result<UnsignedPaymentDisclosure<'_, T, V>, DisclosureError> {
+        let action = self
+            .actions()
+            .get(action_idx)
+            .ok_or(DisclosureError::InvalidActionIndex(action_idx))?;
+        let domain = BundleDomain::for_action(action, self.bundle_version().note_version());
+        let (note, recipient, _) = try_output_recovery_with_ock(
+            &domain,
+            ock,
+            action,
+            &action.encrypted_note().out_ciphertext,
+        )
+        .ok_or(DisclosureError::InvalidOutput)?;
+
+        Ok(UnsignedPaymentDisclosure {
+            bundle: self,
+            action_idx,
+            recipient,
+            esk: note.esk().to_bytes(),
+            message: String::new(),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use alloc::{boxed::Box, vec::Vec};
+
+    use rand::rngs::OsRng;
+
+    use super::{DisclosureError, PaymentDisclosure};
+    use crate::{
+        builder::UnauthorizedBundle,
+        bundle::{tests::builder_with_spend, BundleVersion, Flags},
+        keys::{FullViewingKey, Scope, SpendAuthorizingKey, SpendingKey},
+        memo::Memo,
+        note::{Note, Rho},
+        note_encryption::prf_ock_orchard,
+        value::NoteValue,
+        Address,
+    };
+
+    struct Fixture {
+        sk: SpendingKey,
+        payee: Address,
+        bundle: UnauthorizedBundle<i64>,
+        spend_idx: usize,
+        output_idx: usize,
+    }
+
+    fn fixture() -> Fixture {
+        let mut rng = OsRng;
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let payee =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+
+        let (mut builder, _) = builder_with_spend(
+            &mut rng,
+            &fvk,
+            fvk.address_at(0u32, Scope::External),
+            NoteValue::from_raw(15_000),
+            BundleVersion::orchard_v2(),
+            Flags::ENABLED,
+        );
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::External)),
+                payee,
+                NoteValue::from_raw(10_000),
+                Memo::Arbitrary(Box::new([7; 511])),
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(rng).unwrap().unwrap();
+
+        Fixture {
+            sk,
+            payee,
+            bundle,
+            spend_idx: meta.spend_action_index(0).unwrap(),
+            output_idx: meta.output_action_index(0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn disclosure_round_trip() {
+        let rng = OsRng;
+        let Fixture {
+            sk,
+            payee,
+            bundle,
+            spend_idx,
+            output_idx,
+        } = fixture();
+        let fvk = FullViewingKey::from(&sk);
+        let ask = SpendAuthorizingKey::from(&sk);
+        let alpha = *bundle.actions()[spend_idx].authorization().parts().alpha();
+        let txid = [0x42; 32];
+
+        // Disclose the output with the `ock` recovered from the wallet's `ovk`.
+        let action = &bundle.actions()[output_idx];
+        let ock = prf_ock_orchard(
+            &fvk.to_ovk(Scope::External),
+            action.cv_net(),
+            &action.cmx().to_bytes(),
+            &action.encrypted_note().epk_bytes.into(),
+        );
+        let disclosure = bundle
+            .disclose_output_with_ock(output_idx, &ock)
+            .unwrap()
+            .with_message("withdrawal 1234")
+            .unwrap()
+            .sign(rng, txid, spend_idx, &ask, &alpha)
+            .unwrap();
+
+        let payment = disclosure.verify(&txid, &bundle).unwrap();
+        assert_eq!(*payment.recipient(), payee);
+        assert_eq!(*payment.value(), NoteValue::from_raw(10_000));
+        assert_eq!(
+            payment.memo().parse(),
+            Ok(Memo::Arbitrary(Box::new([7; 511])))
+        );
+        assert_eq!(payment.memo().as_bytes()[0], 0xFF);
+
+        // The encoding round-trips and still verifies.
+        let mut encoded = Vec::new();
+        disclosure.write(&mut encoded).unwrap();
+        let decoded = PaymentDisclosure::read(&encoded[..]).unwrap();
+        assert_eq!(decoded.message(), "withdrawal 1234");
+        assert_eq!(*decoded.txid(), txid);
+        assert!(decoded.verify(&txid, &bundle).is_ok());
+
+        // The disclosure fails for any other transaction.
+        assert_eq!(
+            disclosure.verify(&[0x43; 32], &bundle).unwrap_err(),
+            DisclosureError::TxidMismatch
+        );
+
+        // Changing any signed field invalidates the signature.
+        let mut tampered = encoded.clone();
+        tampered[0] ^= 1;
+        let tampered = PaymentDisclosure::read(&tampered[..]).unwrap();
+        assert_eq!(
+            tampered.verify(tampered.txid(), &bundle).unwrap_err(),
+            DisclosureError::InvalidSignature
+        );
+
+        // The disclosure fails for an action other than the disclosed one.
+        let mut moved = disclosure.clone();
+        moved.action_idx = 1 - output_idx;
+        moved.spend_auth_sig = ask.randomize(&alpha).sign(rng, moved.digest().as_bytes());
+        assert_eq!(
+            moved.verify(&txid, &bundle).unwrap_err(),
+            DisclosureError::InvalidOutput
+        );
+    }
+
+    #[test]
+    fn disclosure_requires_matching_keys() {
+        let rng = OsRng;
+        let Fixture {
+            sk,
+            bundle,
+            spend_idx,
+            output_idx,
+            ..
+        } = fixture();
+        let ask = SpendAuthorizingKey::from(&sk);
+        let alpha = *bundle.actions()[spend_idx].authorization().parts().alpha();
+
+        // A note that the action did not create cannot be disclosed.
+        let foreign_note = Note::new(
+            FullViewingKey::from(&sk).address_at(1u32, Scope::External),
+            NoteValue::from_raw(1),
+            Rho::from_nf_old(*bundle.actions()[output_idx].nullifier()),
+            bundle.bundle_version().note_version(),
+            OsRng,
+        );
+        assert_eq!(
+            bundle
+                .disclose_output(output_idx, &foreign_note)
+                .unwrap_err(),
+            DisclosureError::InvalidOutput
+        );
+        assert_eq!(
+            bundle
+                .disclose_output(bundle.actions().len(), &foreign_note)
+                .unwrap_err(),
+            DisclosureError::InvalidActionIndex(bundle.actions().len())
+        );
+
+        // Only the spend authorizing key of the signing action can sign.
+        let (sent, _, _) = bundle
+            .recover_output_with_ovk(
+                output_idx,
+                &FullViewingKey::from(&sk).to_ovk(Scope::External),
+            )
+            .unwrap();
+        let unsigned = || bundle.disclose_output(output_idx, &sent).unwrap();
+        let other_ask = SpendAuthorizingKey::from(&SpendingKey::random(&mut OsRng));
+        assert_eq!(
+            unsigned()
+                .sign(rng, [0; 32], spend_idx, &other_ask, &alpha)
+                .unwrap_err(),
+            DisclosureError::WrongSpendAuthorizingKey
+        );
+        let other_idx = 1 - spend_idx;
+        assert_eq!(
+            unsigned()
+                .sign(rng, [0; 32], other_idx, &ask, &alpha)
+                .unwrap_err(),
+            DisclosureError::WrongSpendAuthorizingKey
+        );
+        assert!(unsigned()
+            .sign(rng, [0; 32], spend_idx, &ask, &alpha)
+            .is_ok());
+    }
+}
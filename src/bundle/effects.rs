@@ -0,0 +1,287 @@
+//! The effects of a bundle on the balance of an account.
+
+use alloc::vec::Vec;
+
+use getset::Getters;
+
+use super::{Authorization, Bundle, SentOutput, SentOutputKind};
+use crate::{
+    keys::{FullViewingKey, Scope},
+    memo::MemoBytes,
+    note::{Note, Nullifier},
+    value::{BalanceError, NoteValue, ValueSum},
+};
+
+/// A note that an account received in a bundle, found by [`Bundle::account_effects`].
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct ReceivedNote {
+    /// The index of the action within the bundle.
+    action_idx: usize,
+    /// The scope of the account's address that received the note.
+    scope: Scope,
+    /// The received note.
+    note: Note,
+    /// The memo field of the note.
+    memo: MemoBytes,
+}
+
+/// A note of an account that was spent in a bundle, found by [`Bundle::account_effects`].
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct SpentNote {
+    /// The index of the action within the bundle.
+    action_idx: usize,
+    /// The nullifier of the spent note.
+    nullifier: Nullifier,
+    /// The value of the spent note.
+    value: NoteValue,
+}
+
+/// The effects of a bundle on an account, computed by [`Bundle::account_effects`].
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct AccountEffects {
+    /// The notes that the account received, including its own change but not its
+    /// [`SentOutputKind::Fabricated`] outputs.
+    received: Vec<ReceivedNote>,
+    /// The notes of the account that the bundle spent.
+    spent: Vec<SpentNote>,
+    /// The outputs that the account sent, as returned by [`Bundle::sent_outputs`].
+    sent: Vec<SentOutput>,
+    /// The total value of the received notes.
+    received_value: u64,
+    /// The total value of the spent notes.
+    spent_value: u64,
+    /// The total value of the [`SentOutputKind::ExternalPayment`] outputs.
+    paid_value: u64,
+}
+
+impl AccountEffects {
+    /// Returns the net change in the account's balance: the received value minus the
+    /// spent value.
+    pub fn net_value(&self) -> ValueSum {
+        NoteValue::from_raw(self.received_value) - NoteValue::from_raw(self.spent_value)
+    }
+
+    /// Returns the account's share of the value that leaves the Orchard pool: the spent
+    /// value that was neither received back by the account nor paid to a recipient that
+    /// the account sent to. The share is negative when the account received more than it
+    /// spent, whether from other spenders in the bundle or from outside the pool.
+    ///
+    /// When the account made every spend and created every output in the bundle, this
+    /// is the bundle's `value_balance`; for a transaction with no other shielded or
+    /// transparent components, that is the fee. An external payment that was sent
+    /// without an outgoing viewing key cannot be recovered, and so is counted in this
+    /// share.
+    pub fn value_balance_share(&self) -> Result<ValueSum, BalanceError> {
+        let retained = self
+            .received_value
+            .checked_add(self.paid_value)
+            .ok_or(BalanceError::Overflow)?;
+        Ok(NoteValue::from_raw(self.spent_value) - NoteValue::from_raw(retained))
+    }
+}
+
+/// Sums the given note values, failing if the total overflows a `u64`.
+fn total(mut values: impl Iterator<Item = NoteValue>) -> Result<u64, BalanceError> {
+    values
+        .try_fold(0u64, |acc, value| acc.checked_add(value.inner()))
+        .ok_or(BalanceError::Overflow)
+}
+
+impl<T: Authorization, V> Bundle<T, V> {
+    /// Computes the effects of this bundle on the account with the given full viewing
+    /// key.
+    ///
+    /// The received notes are found by trial decryption with the account's external and
+    /// internal incoming viewing keys, and the sent outputs are recovered with its
+    /// outgoing viewing keys as described in [`Bundle::sent_outputs`]. An action spent
+    /// one of the account's notes if `spent_note_value` returns the note's value for the
+    /// action's nullifier; it is typically a lookup in the account's wallet.
+    ///
+    /// The account's [`SentOutputKind::Fabricated`] outputs decrypt with its incoming
+    /// viewing keys, but are sent outputs and are not counted as received.
+    ///
+    /// Returns [`BalanceError::Overflow`] if a total value overflows a `u64`.
+    pub fn account_effects(
+        &self,
+        fvk: &FullViewingKey,
+        spent_note_value: &impl Fn(&Nullifier) -> Option<NoteValue>,
+    ) -> Result<AccountEffects, BalanceError> {
+        let sent = self.sent_outputs(fvk);
+        let fabricated = |action_idx: usize| {
+            sent.iter().any(|output| {
+                *output.action_idx() == action_idx && *output.kind() == SentOutputKind::Fabricated
+            })
+        };
+
+        let ivks = [fvk.to_ivk(Scope::External), fvk.to_ivk(Scope::Internal)];
+        let received: Vec<_> = self
+            .decrypt_outputs_with_keys(&ivks)
+            .into_iter()
+            .filter(|(action_idx, ..)| !fabricated(*action_idx))
+            .map(|(action_idx, ivk, note, _, memo)| ReceivedNote {
+                action_idx,
+                scope: if ivk == ivks[0] {
+                    Scope::External
+                } else {
+                    Scope::Internal
+                },
+                note,
+                memo,
+            })
+            .collect();
+
+        let spent: Vec<_> = self
+            .actions()
+            .iter()
+            .enumerate()
+            .filter_map(|(action_idx, action)| {
+                spent_note_value(action.nullifier()).map(|value| SpentNote {
+                    action_idx,
+                    nullifier: *action.nullifier(),
+                    value,
+                })
+            })
+            .collect();
+
+        let received_value = total(received.iter().map(|r| r.note.value()))?;
+        let spent_value = total(spent.iter().map(|s| s.value))?;
+        let paid_value = total(
+            sent.iter()
+                .filter(|output| *output.kind() == SentOutputKind::ExternalPayment)
+                .map(|output| output.note().value()),
+        )?;
+
+        Ok(AccountEffects {
+            received,
+            spent,
+            sent,
+            received_value,
+            spent_value,
+            paid_value,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use crate::{
+        bundle::{tests::builder_with_spend, BundleVersion, Flags, SentOutputKind},
+        keys::{FullViewingKey, Scope, SpendingKey},
+        memo::Memo,
+        note::Nullifier,
+        value::{NoteValue, ValueSum},
+    };
+
+    #[test]
+    fn account_effects_balance() {
+        let mut rng = OsRng;
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let foreign = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let bundle_version = BundleVersion::orchard_v2();
+
+        let (mut builder, nf) = builder_with_spend(
+            &mut rng,
+            &fvk,
+            fvk.address_at(0u32, Scope::External),
+            NoteValue::from_raw(15_000),
+            bundle_version,
+            bundle_version.default_flags(),
+        );
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::External)),
+                foreign.address_at(0u32, Scope::External),
+                NoteValue::from_raw(7_000),
+                [1; 512],
+            )
+            .unwrap();
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::Internal)),
+                fvk.address_at(0u32, Scope::Internal),
+                NoteValue::from_raw(5_000),
+                Memo::Empty,
+            )
+            .unwrap();
+        let bundle = builder.build::<i64>(rng).unwrap().unwrap().0;
+        let spent_note_value = |n: &Nullifier| (*n == nf).then_some(NoteValue::from_raw(15_000));
+
+        let effects = bundle.account_effects(&fvk, &spent_note_value).unwrap();
+        assert_eq!(effects.received().len(), 1);
+        assert_eq!(*effects.received()[0].scope(), Scope::Internal);
+        assert_eq!(effects.received()[0].memo().parse(), Ok(Memo::Empty));
+        assert_eq!(effects.spent().len(), 1);
+        assert_eq!(effects.spent()[0].nullifier(), &nf);
+        assert!(effects
+            .sent()
+            .iter()
+            .any(|output| *output.kind() == SentOutputKind::ExternalPayment));
+        assert_eq!(*effects.received_value(), 5_000);
+        assert_eq!(*effects.spent_value(), 15_000);
+        assert_eq!(*effects.paid_value(), 7_000);
+        assert_eq!(i64::try_from(effects.net_value()).unwrap(), -10_000);
+        // The account funded the whole bundle, so its share is the value balance.
+        assert_eq!(
+            effects.value_balance_share().unwrap(),
+            ValueSum::from_raw(*bundle.value_balance())
+        );
+
+        let effects = bundle.account_effects(&foreign, &|_| None).unwrap();
+        assert_eq!(effects.received().len(), 1);
+        assert_eq!(*effects.received()[0].scope(), Scope::External);
+        assert!(effects.spent().is_empty());
+        assert!(effects.sent().is_empty());
+        assert_eq!(i64::try_from(effects.net_value()).unwrap(), 7_000);
+        assert_eq!(
+            i64::try_from(effects.value_balance_share().unwrap()).unwrap(),
+            -7_000
+        );
+    }
+
+    #[test]
+    fn fabricated_outputs_are_not_received() {
+        let mut rng = OsRng;
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let change = fvk.address_at(0u32, Scope::Internal);
+
+        // The fabricated output paired with the spend of an internal-scope note decrypts
+        // with the account's internal incoming viewing key.
+        let (mut builder, nf) = builder_with_spend(
+            &mut rng,
+            &fvk,
+            change,
+            NoteValue::from_raw(15_000),
+            BundleVersion::orchard_v3(),
+            Flags::CROSS_ADDRESS_DISABLED,
+        );
+        builder
+            .add_change_output(
+                fvk.clone(),
+                Some(fvk.to_ovk(Scope::Internal)),
+                change,
+                NoteValue::from_raw(5_000),
+                Memo::Empty,
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(rng).unwrap().unwrap();
+        let spent_note_value = |n: &Nullifier| (*n == nf).then_some(NoteValue::from_raw(15_000));
+
+        let effects = bundle.account_effects(&fvk, &spent_note_value).unwrap();
+        let fabricated_idx = meta.spend_action_index(0).unwrap();
+        assert!(effects.sent().iter().any(|output| {
+            *output.action_idx() == fabricated_idx && *output.kind() == SentOutputKind::Fabricated
+        }));
+        assert_eq!(effects.received().len(), 1);
+        assert_eq!(
+            *effects.received()[0].action_idx(),
+            meta.output_action_index(0).unwrap()
+        );
+        assert_eq!(*effects.received_value(), 5_000);
+        assert_eq!(i64::try_from(effects.net_value()).unwrap(), -10_000);
+    }
+}
@@ -0,0 +1,502 @@
+//! Transaction encoding of authorized Orchard bundles.
+//!
+//! The layout follows [Zcash Protocol Spec § 7.1: Transaction Encoding And
+//! Consensus][txencoding] and [ZIP 225]. In a v5 transaction the bundle anchor sits with
+//! the bundle effects, immediately after `valueBalance`; in a v6 transaction it is part of
+//! the authorizing data and follows the binding signature. In both cases the field order
+//! matches the order in which [`hash_bundle_txid_data`] and [`hash_bundle_auth_data`] hash
+//! the same fields.
+//!
+//! [txencoding]: https://zips.z.cash/protocol/protocol.pdf#txnencoding
+//! [ZIP 225]: https://zips.z.cash/zip-0225
+//! [`hash_bundle_txid_data`]: super::commitments::hash_bundle_txid_data
+//! [`hash_bundle_auth_data`]: super::commitments::hash_bundle_auth_data
+
+use alloc::vec::Vec;
+
+use corez::io::{self, Read, Write};
+use nonempty::NonEmpty;
+
+use crate::{
+    action::Action,
+    bundle::{
+        commitments::BundleCommitmentFormat, Authorized, Bundle, BundleVersion, Flags, TxVersion,
+    },
+    note::{ExtractedNoteCommitment, Nullifier, TransmittedNoteCiphertext},
+    primitives::redpallas::{self, SpendAuth},
+    tree::Anchor,
+    value::ValueCommitment,
+    Proof,
+};
+
+/// The largest value a compactSize field may take, as enforced by `zcashd`.
+const MAX_COMPACT_SIZE: u64 = 0x0200_0000;
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes `size` in Bitcoin's canonical compactSize encoding.
+pub(crate) fn write_compact_size<W: Write>(mut writer: W, size: usize) -> io::Result<()> {
+    match size {
+        s if s < 253 => writer.write_all(&[s as u8]),
+        s if s <= 0xFFFF => {
+            writer.write_all(&[253])?;
+            writer.write_all(&(s as u16).to_le_bytes())
+        }
+        s if s <= 0xFFFF_FFFF => {
+            writer.write_all(&[254])?;
+            writer.write_all(&(s as u32).to_le_bytes())
+        }
+        s => {
+            writer.write_all(&[255])?;
+            writer.write_all(&(s as u64).to_le_bytes())
+        }
+    }
+}
+
+/// Reads a compactSize value, rejecting non-canonical encodings and values greater than
+/// [`MAX_COMPACT_SIZE`].
+pub(crate) fn read_compact_size<R: Read>(mut reader: R) -> io::Result<usize> {
+    let mut flag = [0u8; 1];
+    reader.read_exact(&mut flag)?;
+    let (size, min) = match flag[0] {
+        s @ 0..=252 => (u64::from(s), 0),
+        253 => {
+            let mut bytes = [0u8; 2];
+            reader.read_exact(&mut bytes)?;
+            (u64::from(u16::from_le_bytes(bytes)), 253)
+        }
+        254 => {
+            let mut bytes = [0u8; 4];
+            reader.read_exact(&mut bytes)?;
+            (u64::from(u32::from_le_bytes(bytes)), 0x1_0000)
+        }
+        255 => {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            (u64::from_le_bytes(bytes), 0x1_0000_0000)
+        }
+    };
+
+    if size < min {
+        Err(invalid_data("non-canonical compactSize encoding"))
+    } else if size > MAX_COMPACT_SIZE {
+        Err(invalid_data("compactSize value exceeds the maximum"))
+    } else {
+        Ok(size as usize)
+    }
+}
+
+pub(crate) fn read_array<R: Read, const N: usize>(mut reader: R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Returns the commitment format for a bundle of `bundle_version` encoded in a `tx_version`
+/// transaction, which also determines where the anchor is placed on the wire.
+fn encoding_format(
+    bundle_version: BundleVersion,
+    tx_version: TxVersion,
+) -> io::Result<BundleCommitmentFormat> {
+    bundle_version
+        .value_pool()
+        .commitment_format(tx_version)
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Ironwood bundles can only be encoded in a v6 transaction",
+            )
+        })
+}
+
+fn write_action<W: Write>(
+    mut writer: W,
+    action: &Action<redpallas::Signature<SpendAuth>>,
+) -> io::Result<()> {
+    writer.write_all(&action.cv_net().to_bytes())?;
+    writer.write_all(&action.nullifier().to_bytes())?;
+    writer.write_all(&<[u8; 32]>::from(action.rk()))?;
+    writer.write_all(&action.cmx().to_bytes())?;
+    writer.write_all(&action.encrypted_note().epk_bytes)?;
+    writer.write_all(&action.encrypted_note().enc_ciphertext)?;
+    writer.write_all(&action.encrypted_note().out_ciphertext)
+}
+
+/// Reads the effecting data of an action; its spend authorization signature is stored
+/// separately and is read later.
+fn read_action_without_auth<R: Read>(mut reader: R) -> io::Result<Action<()>> {
+    let cv_net = Option::from(ValueCommitment::from_bytes(&read_array(&mut reader)?))
+        .ok_or_else(|| invalid_data("invalid Orchard value commitment"))?;
+    let nf = Option::from(Nullifier::from_bytes(&read_array(&mut reader)?))
+        .ok_or_else(|| invalid_data("invalid Orchard nullifier"))?;
+    let rk = redpallas::VerificationKey::try_from(read_array::<_, 32>(&mut reader)?)
+        .map_err(|_| invalid_data("invalid Orchard spend validating key"))?;
+    let cmx = Option::from(ExtractedNoteCommitment::from_bytes(&read_array(
+        &mut reader,
+    )?))
+    .ok_or_else(|| invalid_data("invalid Orchard note commitment"))?;
+    let encrypted_note = TransmittedNoteCiphertext {
+        epk_bytes: read_array(&mut reader)?,
+        enc_ciphertext: read_array(&mut reader)?,
+        out_ciphertext: read_array(&mut reader)?,
+    };
+
+    Action::from_parts(nf, rk, cmx, encrypted_note, cv_net, ()).map_err(|e| match e {
+        crate::ActionFromPartsError::IdentityRk => {
+            invalid_data("an Orchard action with identity `rk` is not valid")
+        }
+        crate::ActionFromPartsError::InvalidEpk => {
+            invalid_data("an Orchard action's `epk` is not a valid non-identity Pallas point")
+        }
+    })
+}
+
+fn read_anchor<R: Read>(reader: R) -> io::Result<Anchor> {
+    Option::from(Anchor::from_bytes(read_array(reader)?))
+        .ok_or_else(|| invalid_data("invalid Orchard anchor"))
+}
+
+impl<V: Copy + Into<i64>> Bundle<Authorized, V> {
+    /// Writes this bundle in the encoding used by a `tx_version` transaction, as defined in
+    /// [Zcash Protocol Spec § 7.1: Transaction Encoding And Consensus][txencoding].
+    ///
+    /// The flag byte is encoded under the bundle's own [`BundleVersion`]. A transaction that
+    /// has no bundle for this pool instead encodes the single byte `0x00` (an empty action
+    /// vector), which [`Bundle::read`] parses as `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if `tx_version` is not valid
+    /// for the bundle's [`BundleVersion`] (e.g. an Ironwood bundle in a v5 transaction), in
+    /// which case nothing is written. Otherwise, returns any error from `writer`.
+    ///
+    /// [txencoding]: https://zips.z.cash/protocol/protocol.pdf#txnencoding
+    pub fn write<W: Write>(&self, mut writer: W, tx_version: TxVersion) -> io::Result<()> {
+        let format = encoding_format(self.bundle_version, tx_version)?;
+
+        write_compact_size(&mut writer, self.actions.len())?;
+        for action in self.actions.iter() {
+            write_action(&mut writer, action)?;
+        }
+        writer.write_all(&[self.flag_byte()])?;
+        writer.write_all(&self.value_balance.into().to_le_bytes())?;
+        if format.includes_anchor_in_txid_digest() {
+            writer.write_all(&self.anchor.to_bytes())?;
+        }
+
+        let proof = self.authorization.proof().as_ref();
+        write_compact_size(&mut writer, proof.len())?;
+        writer.write_all(proof)?;
+        for action in self.actions.iter() {
+            writer.write_all(&<[u8; 64]>::from(action.authorization()))?;
+        }
+        writer.write_all(&<[u8; 64]>::from(self.authorization.binding_signature()))?;
+        if !format.includes_anchor_in_txid_digest() {
+            writer.write_all(&self.anchor.to_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<V: TryFrom<i64>> Bundle<Authorized, V> {
+    /// Reads a bundle of `bundle_version` from the encoding used by a `tx_version`
+    /// transaction, as defined in [Zcash Protocol Spec § 7.1: Transaction Encoding And
+    /// Consensus][txencoding].
+    ///
+    /// Returns `Ok(None)` if the encoding has no actions, i.e. the transaction has no bundle
+    /// for this pool.
+    ///
+    /// Parsing applies the same checks as [`Bundle::try_from_parts`]: the flag byte must be
+    /// valid under `bundle_version`, and for every version that enforces it the proof must
+    /// have exactly [`Proof::expected_proof_size`] bytes. The proof length is checked before
+    /// the proof is read, so a padded proof is rejected without being buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if `tx_version` is not valid
+    /// for `bundle_version`, or of kind [`io::ErrorKind::InvalidData`] if the encoding is not
+    /// a canonical encoding of a valid bundle. Otherwise, returns any error from `reader`.
+    ///
+    /// [txencoding]: https://zips.z.cash/protocol/protocol.pdf#txnencoding
+    pub fn read<R: Read>(
+        mut reader: R,
+        bundle_version: BundleVersion,
+        tx_version: TxVersion,
+    ) -> io::Result<Option<Self>> {
+        let format = encoding_format(bundle_version, tx_version)?;
+
+        let num_actions = read_compact_size(&mut reader)?;
+        if num_actions == 0 {
+            return Ok(None);
+        }
+        // Each action occupies 820 bytes, so `num_actions` is bounded by the reader's length;
+        // push rather than preallocate so that a bogus count cannot force a large allocation.
+        let mut actions = Vec::new();
+        for _ in 0..num_actions {
+            actions.push(read_action_without_auth(&mut reader)?);
+        }
+
+        let flags = Flags::from_byte(read_array::<_, 1>(&mut reader)?[0], bundle_version)
+            .ok_or_else(|| invalid_data("invalid Orchard flags for the bundle version"))?;
+        let value_balance = V::try_from(i64::from_le_bytes(read_array(&mut reader)?))
+            .map_err(|_| invalid_data("Orchard value balance out of range"))?;
+        let mut anchor = if format.includes_anchor_in_txid_digest() {
+            Some(read_anchor(&mut reader)?)
+        } else {
+            None
+        };
+
+        let proof_len = read_compact_size(&mut reader)?;
+        if bundle_version.enforces_canonical_proof_size()
+            && proof_len != Proof::expected_proof_size(num_actions)
+        {
+            return Err(invalid_data("Orchard proof has non-canonical length"));
+        }
+        let mut proof = vec![0u8; proof_len];
+        reader.read_exact(&mut proof)?;
+
+        let actions = actions
+            .into_iter()
+            .map(|action| {
+                let sig = redpallas::Signature::<SpendAuth>::from(read_array(&mut reader)?);
+                Ok(action.map(|()| sig))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let binding_signature = redpallas::Signature::from(read_array(&mut reader)?);
+        if anchor.is_none() {
+            anchor = Some(read_anchor(&mut reader)?);
+        }
+
+        Bundle::try_from_parts(
+            NonEmpty::from_vec(actions).expect("num_actions is nonzero"),
+            flags,
+            value_balance,
+            anchor.expect("the anchor is read in every format"),
+            Authorized::from_parts(Proof::new(proof), binding_signature),
+            bundle_version,
+        )
+        .map(Some)
+        .map_err(|_| invalid_data("invalid Orchard bundle"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use nonempty::NonEmpty;
+    use proptest::prelude::*;
+
+    use super::{read_compact_size, write_compact_size};
+    use crate::{
+        action::Action,
+        bundle::{testing::arb_bundle, Authorized, Bundle, BundleVersion, Flags, TxVersion},
+        note::{ExtractedNoteCommitment, Nullifier, TransmittedNoteCiphertext},
+        primitives::redpallas::{self, SpendAuth},
+        value::ValueCommitment,
+        Anchor, Proof,
+    };
+
+    /// Builds a bundle from the actions in the note encryption test vectors, with a fake
+    /// proof of canonical length and placeholder signatures.
+    fn test_vector_bundle(bundle_version: BundleVersion) -> Bundle<Authorized, i64> {
+        let actions = crate::test_vectors::note_encryption::test_vectors()
+            .into_iter()
+            .enumerate()
+            .map(|(i, tv)| {
+                Action::from_parts(
+                    Nullifier::from_bytes(&tv.nf_old).unwrap(),
+                    redpallas::VerificationKey::dummy(),
+                    ExtractedNoteCommitment::from_bytes(&tv.cmx).unwrap(),
+                    TransmittedNoteCiphertext {
+                        epk_bytes: tv.ephemeral_key,
+                        enc_ciphertext: tv.c_enc,
+                        out_ciphertext: tv.c_out,
+                    },
+                    ValueCommitment::from_bytes(&tv.cv_net).unwrap(),
+                    redpallas::Signature::<SpendAuth>::from([i as u8; 64]),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let num_actions = actions.len();
+
+        Bundle::try_from_parts(
+            NonEmpty::from_vec(actions).unwrap(),
+            bundle_version.default_flags(),
+            -12345,
+            Anchor::from_bytes([7u8; 32]).unwrap(),
+            Authorized::from_parts(
+                Proof::new(vec![0xaa; Proof::expected_proof_size(num_actions)]),
+                redpallas::Signature::from([0xbb; 64]),
+            ),
+            bundle_version,
+        )
+        .unwrap()
+    }
+
+    fn encode(bundle: &Bundle<Authorized, i64>, tx_version: TxVersion) -> Vec<u8> {
+        let mut bytes = vec![];
+        bundle.write(&mut bytes, tx_version).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn compact_size_round_trip() {
+        for size in [0, 1, 252, 253, 0xFFFF, 0x1_0000, 0x0200_0000] {
+            let mut bytes = vec![];
+            write_compact_size(&mut bytes, size).unwrap();
+            assert_eq!(read_compact_size(&bytes[..]).unwrap(), size);
+        }
+
+        // Non-canonical encodings and oversized values are rejected.
+        assert!(read_compact_size(&[253, 0x10, 0x00][..]).is_err());
+        assert!(read_compact_size(&[254, 0xFF, 0xFF, 0x00, 0x00][..]).is_err());
+        assert!(read_compact_size(&[254, 0x01, 0x00, 0x00, 0x02][..]).is_err());
+    }
+
+    #[test]
+    fn test_vector_actions_round_trip() {
+        for (bundle_version, tx_version) in [
+            (BundleVersion::orchard_v2(), TxVersion::V5),
+            (BundleVersion::orchard_v3(), TxVersion::V5),
+            (BundleVersion::orchard_v3(), TxVersion::V6),
+            (BundleVersion::ironwood_v3(), TxVersion::V6),
+        ] {
+            let bundle = test_vector_bundle(bundle_version);
+            let bytes = encode(&bundle, tx_version);
+
+            // The first action follows the one-byte action count, in ZIP 225 field order.
+            let tv = &crate::test_vectors::note_encryption::test_vectors()[0];
+            assert_eq!(bytes[0] as usize, bundle.actions().len());
+            assert_eq!(&bytes[1..33], &tv.cv_net);
+            assert_eq!(&bytes[33..65], &tv.nf_old);
+            assert_eq!(&bytes[97..129], &tv.cmx);
+            assert_eq!(&bytes[129..161], &tv.ephemeral_key);
+            assert_eq!(&bytes[161..741], &tv.c_enc);
+            assert_eq!(&bytes[741..821], &tv.c_out);
+
+            // The anchor follows the value balance in v5, and ends the encoding in v6.
+            let effects_end = 1 + 820 * bundle.actions().len() + 1 + 8;
+            let anchor = bundle.anchor().to_bytes();
+            match tx_version {
+                TxVersion::V5 => assert_eq!(&bytes[effects_end..effects_end + 32], &anchor),
+                TxVersion::V6 => assert_eq!(&bytes[bytes.len() - 32..], &anchor),
+            }
+
+            let parsed = Bundle::<Authorized, i64>::read(&bytes[..], bundle_version, tx_version)
+                .unwrap()
+                .expect("bundle has actions");
+            assert_eq!(encode(&parsed, tx_version), bytes);
+            assert_eq!(parsed.bundle_version(), bundle_version);
+            assert_eq!(
+                <[u8; 32]>::from(parsed.commitment(tx_version).unwrap()),
+                <[u8; 32]>::from(bundle.commitment(tx_version).unwrap()),
+            );
+            assert_eq!(
+                parsed.authorizing_commitment(tx_version).unwrap().0,
+                bundle.authorizing_commitment(tx_version).unwrap().0,
+            );
+        }
+    }
+
+    #[test]
+    fn absent_bundle_reads_as_none() {
+        let parsed =
+            Bundle::<Authorized, i64>::read(&[0u8][..], BundleVersion::orchard_v2(), TxVersion::V5)
+                .unwrap();
+        assert!(parsed.is_none());
+    }
+
+    #[test]
+    fn ironwood_cannot_be_encoded_in_v5() {
+        let bundle = test_vector_bundle(BundleVersion::ironwood_v3());
+        let mut bytes = vec![];
+        assert_eq!(
+            bundle.write(&mut bytes, TxVersion::V5).unwrap_err().kind(),
+            corez::io::ErrorKind::InvalidInput
+        );
+        assert!(bytes.is_empty());
+        assert!(Bundle::<Authorized, i64>::read(
+            &[0u8][..],
+            BundleVersion::ironwood_v3(),
+            TxVersion::V5
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn read_rejects_noncanonical_proof_and_flags() {
+        let bundle_version = BundleVersion::orchard_v2();
+        let bundle = test_vector_bundle(bundle_version);
+        let bytes = encode(&bundle, TxVersion::V5);
+        let effects_end = 1 + 820 * bundle.actions().len();
+
+        // Bit 2 is reserved for the Orchard pool.
+        let mut bad_flags = bytes.clone();
+        bad_flags[effects_end] |= 0b100;
+        assert!(
+            Bundle::<Authorized, i64>::read(&bad_flags[..], bundle_version, TxVersion::V5).is_err()
+        );
+
+        // A padded proof is rejected: splice one extra byte into the proof and bump its
+        // compactSize length (which is three bytes long for these proof sizes).
+        let proof_len_at = effects_end + 1 + 8 + 32;
+        let proof_len = Proof::expected_proof_size(bundle.actions().len());
+        let mut padded = bytes[..proof_len_at].to_vec();
+        write_compact_size(&mut padded, proof_len + 1).unwrap();
+        padded.push(0);
+        padded.extend_from_slice(&bytes[proof_len_at + 3..]);
+        assert!(
+            Bundle::<Authorized, i64>::read(&padded[..], bundle_version, TxVersion::V5).is_err()
+        );
+
+        // The historical pool parses the same padded proof.
+        let insecure = Bundle::<Authorized, i64>::read(
+            &padded[..],
+            BundleVersion::orchard_insecure_v1(),
+            TxVersion::V5,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            insecure.authorization().proof().as_ref().len(),
+            proof_len + 1
+        );
+        assert_eq!(*insecure.flags(), Flags::ENABLED);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn bundle_round_trip(bundle in arb_bundle(3)) {
+            let bundle_version = bundle.bundle_version();
+            // The generated value balance may exceed the range of an encoded `valueBalance`.
+            let bundle = bundle
+                .try_map_value_balance(|v| {
+                    Ok::<_, core::convert::Infallible>(i64::try_from(v).unwrap_or(i64::MIN))
+                })
+                .unwrap();
+            for tx_version in [TxVersion::V5, TxVersion::V6] {
+                let mut bytes = vec![];
+                if bundle.write(&mut bytes, tx_version).is_err() {
+                    // Only Ironwood bundles cannot be encoded in a v5 transaction.
+                    prop_assert_eq!(bundle_version, BundleVersion::ironwood_v3());
+                    prop_assert_eq!(tx_version, TxVersion::V5);
+                    continue;
+                }
+                let parsed = Bundle::<Authorized, i64>::read(&bytes[..], bundle_version, tx_version)
+                    .unwrap()
+                    .unwrap();
+                prop_assert_eq!(encode(&parsed, tx_version), bytes);
+                prop_assert_eq!(parsed.flags(), bundle.flags());
+                prop_assert_eq!(parsed.anchor(), bundle.anchor());
+                prop_assert_eq!(parsed.value_balance(), bundle.value_balance());
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Recovery of the outputs that an account sent in a bundle.
+
+use alloc::vec::Vec;
+
+use getset::Getters;
+
+use super::{Authorization, Bundle};
+use crate::{
+    keys::{FullViewingKey, Scope},
+    memo::MemoBytes,
+    note::Note,
+};
+
+/// The classification of an output recovered by [`Bundle::sent_outputs`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SentOutputKind {
+    /// A payment to an address not owned by the account.
+    ExternalPayment,
+    /// An output to an address owned by the account, in the given scope, such as change.
+    Change(Scope),
+    /// The zero-valued output that the builder pairs with a real spend in a bundle that
+    /// disables cross-address transfers, addressed to the spent note's own receiver.
+    Fabricated,
+}
+
+/// An output that an account sent in a bundle, recovered by [`Bundle::sent_outputs`].
+#[derive(Clone, Debug, Getters)]
+#[getset(get = "pub")]
+pub struct SentOutput {
+    /// The index of the action within the bundle.
+    action_idx: usize,
+    /// The classification of the output.
+    kind: SentOutputKind,
+    /// The note that the output created.
+    note: Note,
+    /// The memo field of the output, or `None` for a [`SentOutputKind::Fabricated`]
+    /// output, whose memo is meaningless.
+    memo: Option<MemoBytes>,
+}
+
+impl<T: Authorization, V> Bundle<T, V> {
+    /// Recovers the outputs of this bundle that were sent by the account with the given
+    /// full viewing key, and classifies each one.
+    ///
+    /// Every output whose `out_ciphertext` can be decrypted with the account's external
+    /// or internal outgoing viewing key is returned, as an
+    /// [`SentOutputKind::ExternalPayment`] or a [`SentOutputKind::Change`] according to
+    /// whether the account owns the recipient.
+    ///
+    /// In a bundle that disables cross-address transfers, the builder creates
+    /// [`SentOutputKind::Fabricated`] outputs without an outgoing viewing key, so they are
+    /// instead found by decrypting `enc_ciphertext` with the account's incoming viewing
+    /// keys: a zero-valued output found only this way is fabricated. A zero-valued output
+    /// recovered with an outgoing viewing key, such as zero-valued change, is classified
+    /// like any other recovered output. The fabricated output paired with a spend of an
+    /// external-scope note has a randomized `enc_ciphertext`, and is not returned.
+    ///
+    /// The outputs are returned in action order.
+    pub fn sent_outputs(&self, fvk: &FullViewingKey) -> Vec<SentOutput> {
+        let restricted = !self.flags().cross_address_enabled();
+        let ovks = [fvk.to_ovk(Scope::External), fvk.to_ovk(Scope::Internal)];
+        let ivks = [fvk.to_ivk(Scope::External), fvk.to_ivk(Scope::Internal)];
+
+        let mut recovered = self.recover_outputs_with_ovks(&ovks).into_iter().peekable();
+        (0..self.actions().len())
+            .filter_map(|action_idx| {
+                let (note, memo, fabricated) =
+                    match recovered.next_if(|(idx, ..)| *idx == action_idx) {
+                        Some((_, _, note, _, memo)) => (note, memo, false),
+                        None if restricted => {
+                            let (note, _, memo) = ivks
+                                .iter()
+                                .find_map(|ivk| self.decrypt_output_with_key(action_idx, ivk))?;
+                            if note.value().inner() != 0 {
+                                // Only the zero-valued outputs are fabricated; the others
+                                // are notes that the account received.
+                                return None;
+                            }
+                            (note, memo, true)
+                        }
+                        None => return None,
+                    };
+
+                let kind = if fabricated {
+                    SentOutputKind::Fabricated
+                } else {
+                    match fvk.scope_for_address(&note.recipient()) {
+                        Some(scope) => SentOutputKind::Change(scope),
+                        None => SentOutputKind::ExternalPayment,
+                    }
+                };
+                let memo = (!fabricated).then_some(memo);
+
+                Some(SentOutput {
+                    action_idx,
+                    kind,
+                    note,
+                    memo,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use rand::rngs::OsRng;
+
+    use super::SentOutputKind;
+    use crate::{
+        builder::{Builder, BundleType},
+        bundle::{tests::builder_with_spend, BundleVersion, Flags},
+        keys::{FullViewingKey, Scope, SpendingKey},
+        memo::Memo,
+        tree::Anchor,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn sent_outputs_are_classified() {
+        let mut rng = OsRng;
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let foreign = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let payee = foreign.address_at(0u32, Scope::External);
+        let change = fvk.address_at(0u32, Scope::Internal);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT,
+            BundleVersion::orchard_v2(),
+            Flags::SPENDS_DISABLED,
+            Anchor::empty_tree(),
+        )
+        .unwrap();
+        let external_ovk = Some(fvk.to_ovk(Scope::External));
+        let internal_ovk = Some(fvk.to_ovk(Scope::Internal));
+        builder
+            .add_output(external_ovk, payee, NoteValue::from_raw(7_000), [1; 512])
+            .unwrap();
+        builder
+            .add_output(internal_ovk, change, NoteValue::from_raw(5_000), [2; 512])
+            .unwrap();
+        // Outputs sent without an outgoing viewing key cannot be recovered.
+        builder
+            .add_output(None, payee, NoteValue::from_raw(3_000), [3; 512])
+            .unwrap();
+        let bundle = builder.build::<i64>(rng).unwrap().unwrap().0;
+
+        let sent = bundle.sent_outputs(&fvk);
+        let summary: Vec<_> = sent
+            .iter()
+            .map(|output| {
+                (
+                    *output.kind(),
+                    output.note().value().inner(),
+                    output.memo().map(<[u8; 512]>::from),
+                )
+            })
+            .collect();
+        assert_eq!(sent.len(), 2);
+        assert!(summary.contains(&(SentOutputKind::ExternalPayment, 7_000, Some([1; 512]))));
+        assert!(summary.contains(&(
+            SentOutputKind::Change(Scope::Internal),
+            5_000,
+            Some([2; 512])
+        )));
+        assert!(sent
+            .windows(2)
+            .all(|w| w[0].action_idx() < w[1].action_idx()));
+        assert!(bundle.sent_outputs(&foreign).is_empty());
+    }
+
+    #[test]
+    fn fabricated_outputs_are_identified() {
+        let mut rng = OsRng;
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let change = fvk.address_at(0u32, Scope::Internal);
+        let bundle_version = BundleVersion::orchard_v3();
+        let memo: Memo = "Change".parse().unwrap();
+
+        // Spend an internal-scope note, whose fabricated output remains decryptable.
+        let (mut builder, _) = builder_with_spend(
+            &mut rng,
+            &fvk,
+            change,
+            NoteValue::from_raw(15_000),
+            bundle_version,
+            Flags::CROSS_ADDRESS_DISABLED,
+        );
+        builder
+            .add_change_output(
+                fvk.clone(),
+                Some(fvk.to_ovk(Scope::Internal)),
+                change,
+                NoteValue::from_raw(5_000),
+                &memo,
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(rng).unwrap().unwrap();
+
+        let sent = bundle.sent_outputs(&fvk);
+        assert_eq!(sent.len(), 2);
+        for output in &sent {
+            if *output.action_idx() == meta.spend_action_index(0).unwrap() {
+                assert_eq!(*output.kind(), SentOutputKind::Fabricated);
+                assert_eq!(output.note().value(), NoteValue::ZERO);
+                assert!(output.memo().is_none());
+            } else {
+                assert_eq!(*output.action_idx(), meta.output_action_index(0).unwrap());
+                assert_eq!(*output.kind(), SentOutputKind::Change(Scope::Internal));
+                assert_eq!(output.note().value(), NoteValue::from_raw(5_000));
+                assert_eq!(output.memo().map(|m| m.parse()), Some(Ok(memo.clone())));
+            }
+        }
+    }
+
+    #[test]
+    fn zero_valued_change_is_not_fabricated() {
+        let mut rng = OsRng;
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let change = fvk.address_at(0u32, Scope::Internal);
+        let memo: Memo = "Zero change".parse().unwrap();
+
+        let (mut builder, _) = builder_with_spend(
+            &mut rng,
+            &fvk,
+            change,
+            NoteValue::from_raw(15_000),
+            BundleVersion::orchard_v3(),
+            Flags::CROSS_ADDRESS_DISABLED,
+        );
+        builder
+            .add_change_output(
+                fvk.clone(),
+                Some(fvk.to_ovk(Scope::Internal)),
+                change,
+                NoteValue::ZERO,
+                &memo,
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(rng).unwrap().unwrap();
+
+        let change_idx = meta.output_action_index(0).unwrap();
+        let output = bundle
+            .sent_outputs(&fvk)
+            .into_iter()
+            .find(|output| *output.action_idx() == change_idx)
+            .unwrap();
+        assert_eq!(*output.kind(), SentOutputKind::Change(Scope::Internal));
+        assert_eq!(output.note().value(), NoteValue::ZERO);
+        assert_eq!(output.memo().map(|m| m.parse()), Some(Ok(memo)));
+    }
+}
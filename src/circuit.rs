@@ -1,7 +1,9 @@
 //! The Orchard Action circuit implementation.
 
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
+use std::io::{self, Read, Write};
 
+use blake2b_simd::Params as Blake2bParams;
 use group::{Curve, GroupEncoding};
 use halo2_proofs::{
     circuit::{floor_planner, Layouter, Value},
@@ -1087,7 +1089,18 @@ impl VerifyingKey {
     ///
     /// See [`OrchardCircuitVersion`] for which version to use.
     pub fn build(circuit_version: OrchardCircuitVersion) -> Self {
-        let params = halo2_proofs::poly::commitment::Params::new(K);
+        Self::build_with_params(&CommitmentParams::generate(), circuit_version)
+    }
+
+    /// Builds the verifying key for the given circuit version from commitment parameters
+    /// that were generated or read earlier.
+    ///
+    /// This skips generating the parameters but still runs key generation.
+    pub fn build_with_params(
+        params: &CommitmentParams,
+        circuit_version: OrchardCircuitVersion,
+    ) -> Self {
+        let params = params.0.clone();
         let circuit = Circuit::empty(circuit_version);
 
         let vk = plonk::keygen_vk(&params, &circuit).unwrap();
@@ -1126,7 +1139,18 @@ impl ProvingKey {
     ///
     /// See [`OrchardCircuitVersion`] for which version to use.
     pub fn build(circuit_version: OrchardCircuitVersion) -> Self {
-        let params = halo2_proofs::poly::commitment::Params::new(K);
+        Self::build_with_params(&CommitmentParams::generate(), circuit_version)
+    }
+
+    /// Builds the proving key for the given circuit version from commitment parameters
+    /// that were generated or read earlier.
+    ///
+    /// This skips generating the parameters but still runs key generation.
+    pub fn build_with_params(
+        params: &CommitmentParams,
+        circuit_version: OrchardCircuitVersion,
+    ) -> Self {
+        let params = params.0.clone();
         let circuit = Circuit::empty(circuit_version);
 
         let vk = plonk::keygen_vk(&params, &circuit).unwrap();
@@ -1150,6 +1174,90 @@ impl ProvingKey {
     }
 }
 
+/// The commitment parameters that the Orchard Action circuit keys are built from.
+///
+/// The parameters do not depend on the circuit version. Generating them is part of the
+/// cost of [`VerifyingKey::build`] and [`ProvingKey::build`], so callers that build keys
+/// repeatedly can generate the parameters once, cache them with [`Self::write`], and build
+/// keys with [`VerifyingKey::build_with_params`] and [`ProvingKey::build_with_params`].
+///
+/// This does not cache the keys themselves: `halo2_proofs` cannot serialize them, so
+/// building a key from cached parameters still runs key generation.
+#[derive(Clone, Debug)]
+pub struct CommitmentParams(halo2_proofs::poly::commitment::Params<vesta::Affine>);
+
+impl CommitmentParams {
+    /// Generates the commitment parameters for the Orchard Action circuit.
+    pub fn generate() -> Self {
+        CommitmentParams(halo2_proofs::poly::commitment::Params::new(K))
+    }
+
+    /// Writes these commitment parameters.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&COMMITMENT_PARAMS_MAGIC)?;
+        self.0.write(&mut writer)
+    }
+
+    /// Reads commitment parameters written by [`Self::write`].
+    ///
+    /// The result is rejected unless it is identical to the parameters that
+    /// [`Self::generate`] produces.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != COMMITMENT_PARAMS_MAGIC {
+            return Err(invalid("not encoded Orchard commitment parameters"));
+        }
+
+        // Check the parameter size before `Params::read` allocates for it.
+        let mut k = [0; 4];
+        reader.read_exact(&mut k)?;
+        if u32::from_le_bytes(k) != K {
+            return Err(invalid("commitment parameters have the wrong size"));
+        }
+        let params = halo2_proofs::poly::commitment::Params::read(&mut (&k[..]).chain(reader))?;
+
+        if params_digest(&params) == COMMITMENT_PARAMS_DIGEST {
+            Ok(CommitmentParams(params))
+        } else {
+            Err(invalid(
+                "commitment parameters do not match the pinned digest",
+            ))
+        }
+    }
+}
+
+/// The leading bytes of encoded [`CommitmentParams`].
+const COMMITMENT_PARAMS_MAGIC: [u8; 4] = *b"OrCP";
+
+const COMMITMENT_PARAMS_PERSONALIZATION: &[u8; 16] = b"Orchard_CmParams";
+
+// The digest of the commitment parameters. This changes only if `K` or the parameter
+// generation in `halo2_proofs` changes, in which case `commitment_params_digest_is_pinned`
+// prints the new value.
+const COMMITMENT_PARAMS_DIGEST: [u8; 32] = [
+    0xa5, 0x54, 0x4b, 0xd5, 0x8f, 0x01, 0x83, 0x0e, 0x18, 0x1d, 0x94, 0xd1, 0x93, 0x90, 0xda, 0xb7,
+    0x10, 0xe6, 0xe7, 0x07, 0x15, 0xd0, 0x8b, 0x99, 0x77, 0x52, 0x06, 0x72, 0xab, 0x2f, 0x6a, 0xd0,
+];
+
+/// Computes the digest of the encoding of `params`.
+fn params_digest(params: &halo2_proofs::poly::commitment::Params<vesta::Affine>) -> [u8; 32] {
+    let mut hasher = Blake2bParams::new()
+        .hash_length(32)
+        .personal(COMMITMENT_PARAMS_PERSONALIZATION)
+        .to_state();
+    params
+        .write(&mut hasher)
+        .expect("writing to a hasher never fails");
+    hasher
+        .finalize()
+        .as_bytes()
+        .try_into()
+        .expect("digest is 32 bytes")
+}
+
 /// Public inputs to the Orchard Action circuit.
 ///
 /// # Invariants
@@ -1179,7 +1287,7 @@ impl Instance {
     /// [`Flags::cross_address_enabled`]. If cross-address transfers are disabled,
     /// callers must use a proving or verifying key whose circuit version supports the
     /// cross-address restriction; [`Proof::create`], [`Proof::verify`], and
-    /// [`crate::bundle::BatchValidator`] enforce this.
+    /// [`crate::bundle::BatchValidator`] (and its multi-version counterpart) enforce this.
     ///
     /// Returns `None` if `rk` is the identity [`pasta_curves::pallas::Point`].
     /// zcashd v6.12.1 and Zebra 4.3.1 both added a consensus rule rejecting
@@ -1365,17 +1473,18 @@ impl Proof {
 
     /// Adds this proof to the given batch for verification with the given instances.
     ///
-    /// Internal to [`BatchValidator`], which is the only public batch path. A raw batch
+    /// Internal to [`BatchValidator`] and [`MultiVersionBatchValidator`], which are the only
+    /// public batch paths. A raw batch
     /// does not know which [`VerifyingKey`] it will be finalized with, so it cannot enforce
     /// that instances disabling cross-address transfers are only finalized with a key whose
     /// circuit version constrains the `disableCrossAddress` public input (see
-    /// [`OrchardCircuitVersion::supports_cross_address_restriction`]). [`BatchValidator`]
-    /// binds its key at construction and rejects such bundles in [`add_bundle`] before they
-    /// reach this method; exposing this directly would let a caller sidestep that check by
+    /// [`OrchardCircuitVersion::supports_cross_address_restriction`]). Both validators bind
+    /// their keys at construction and reject such bundles in `add_bundle` before they reach
+    /// this method; exposing this directly would let a caller sidestep that check by
     /// finalizing the batch against an unsupported key.
     ///
     /// [`BatchValidator`]: crate::bundle::BatchValidator
-    /// [`add_bundle`]: crate::bundle::BatchValidator::add_bundle
+    /// [`MultiVersionBatchValidator`]: crate::bundle::MultiVersionBatchValidator
     pub(crate) fn add_to_batch(
         &self,
         batch: &mut BatchVerifier<vesta::Affine>,
@@ -1408,7 +1517,10 @@ mod tests {
     use pasta_curves::{pallas, vesta};
     use rand::{rngs::OsRng, RngCore};
 
-    use super::{Circuit, Instance, OrchardCircuitVersion, Proof, ProvingKey, VerifyingKey, K};
+    use super::{
+        Circuit, CommitmentParams, Instance, OrchardCircuitVersion, Proof, ProvingKey,
+        VerifyingKey, K,
+    };
     use crate::{
         bundle::{BundleVersion, Flags},
         keys::SpendValidatingKey,
@@ -1746,6 +1858,58 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn commitment_params_digest_is_pinned() {
+        let params = CommitmentParams::generate();
+        assert_eq!(
+            super::params_digest(&params.0),
+            super::COMMITMENT_PARAMS_DIGEST,
+            "{:02x?}",
+            super::params_digest(&params.0),
+        );
+    }
+
+    #[test]
+    fn keys_build_from_cached_params() {
+        let mut rng = OsRng;
+        let circuit_version = OrchardCircuitVersion::PostNu6_3;
+
+        let mut params_bytes = vec![];
+        CommitmentParams::generate()
+            .write(&mut params_bytes)
+            .unwrap();
+        let params = CommitmentParams::read(&params_bytes[..]).unwrap();
+
+        let pk = ProvingKey::build_with_params(&params, circuit_version);
+        let vk = VerifyingKey::build_with_params(&params, circuit_version);
+        assert_eq!(pk.circuit_version(), circuit_version);
+        assert_eq!(vk.circuit_version(), circuit_version);
+        assert_eq!(
+            format!("{:?}", vk.vk.pinned()),
+            format!("{:?}", VerifyingKey::build(circuit_version).vk.pinned())
+        );
+
+        let (circuit, instance) = generate_circuit_instance(&mut rng, circuit_version);
+        let instances = core::slice::from_ref(&instance);
+        let proof =
+            Proof::create(&pk, core::slice::from_ref(&circuit), instances, &mut rng).unwrap();
+        assert!(proof.verify(&vk, instances).is_ok());
+
+        // An encoding without the expected leading bytes is rejected.
+        let mut relabeled = params_bytes.clone();
+        relabeled[0] ^= 1;
+        assert!(CommitmentParams::read(&relabeled[..]).is_err());
+
+        // Corrupted parameters are rejected, even if they decode.
+        let mut corrupted = params_bytes.clone();
+        let u = corrupted.len() - 32;
+        corrupted[u..].copy_from_slice(&params_bytes[u - 32..u]);
+        assert!(CommitmentParams::read(&corrupted[..]).is_err());
+
+        // Truncated parameters are rejected.
+        assert!(CommitmentParams::read(&params_bytes[..params_bytes.len() - 1]).is_err());
+    }
+
     // Set ORCHARD_CIRCUIT_TEST_GENERATE_NEW_PROOF to regenerate the pinned circuit description
     // for this version.
     fn pinned_circuit_description(
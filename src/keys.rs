@@ -4,7 +4,7 @@ use alloc::boxed::Box;
 use alloc::vec::Vec;
 use corez::io::{self, Read, Write};
 
-use ::zip32::{AccountId, ChildIndex};
+use ::zip32::AccountId;
 use aes::Aes256;
 use blake2b_simd::{Hash as Blake2bHash, Params};
 use fpe::ff1::{BinaryNumeralString, FF1};
@@ -32,8 +32,15 @@ use crate::{
 
 pub use ::zip32::{DiversifierIndex, Scope};
 
+mod addresses;
+pub use addresses::Addresses;
+
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "encoding")]
+pub use encoding::{Network, UnifiedKeyError};
+
 const KDF_ORCHARD_PERSONALIZATION: &[u8; 16] = b"Zcash_OrchardKDF";
-const ZIP32_PURPOSE: u32 = 32;
 
 /// A spending key, from which all key material is derived.
 ///
@@ -99,17 +106,8 @@ impl SpendingKey {
         coin_type: u32,
         account: AccountId,
     ) -> Result<Self, zip32::Error> {
-        if coin_type >= (1 << 31) {
-            return Err(zip32::Error::InvalidChildIndex(coin_type));
-        }
-
-        // Call zip32 logic
-        let path = &[
-            ChildIndex::hardened(ZIP32_PURPOSE),
-            ChildIndex::hardened(coin_type),
-            ChildIndex::hardened(account.into()),
-        ];
-        ExtendedSpendingKey::from_path(seed, path).map(|esk| esk.sk())
+        let path = zip32::DerivationPath::for_account(coin_type, account)?;
+        ExtendedSpendingKey::from_path(seed, path.as_slice()).map(|esk| esk.sk())
     }
 }
 
@@ -453,6 +451,20 @@ impl FullViewingKey {
         Some(fvk)
     }
 
+    /// Returns the fingerprint of this full viewing key, as specified in
+    /// [ZIP32][orchardkeyidentifiers].
+    ///
+    /// [orchardkeyidentifiers]: https://zips.z.cash/zip-0032#orchard-key-identifiers
+    pub fn fingerprint(&self) -> zip32::FvkFingerprint {
+        self.into()
+    }
+
+    /// Returns the tag of this full viewing key, which is the parent tag of the extended
+    /// spending keys derived from its extended spending key.
+    pub fn tag(&self) -> zip32::FvkTag {
+        self.fingerprint().tag()
+    }
+
     /// Derives an internal full viewing key from a full viewing key, as specified in
     /// [ZIP32][orchardinternalfullviewingkey]. Internal use only.
     ///
@@ -824,6 +836,10 @@ impl EphemeralSecretKey {
         NonZeroPallasScalar::from_bytes(bytes).map(EphemeralSecretKey)
     }
 
+    pub(crate) fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_repr()
+    }
+
     pub(crate) fn derive_public(&self, g_d: NonIdentityPallasPoint) -> EphemeralPublicKey {
         EphemeralPublicKey(ka_orchard(&self.0, &g_d))
     }
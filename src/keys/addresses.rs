@@ -0,0 +1,143 @@
+//! Sequential generation of diversified payment addresses.
+
+use super::{DiversifierIndex, FullViewingKey, IncomingViewingKey, Scope};
+use crate::address::Address;
+
+/// An iterator over the payment addresses of an [`IncomingViewingKey`], in order of
+/// increasing diversifier index.
+///
+/// Every diversifier index yields a valid Orchard address, so the iterator only ends
+/// after the maximum index (2^88 - 1).
+#[derive(Clone, Debug)]
+pub struct Addresses {
+    ivk: IncomingViewingKey,
+    next: Option<DiversifierIndex>,
+}
+
+impl Addresses {
+    fn new(ivk: IncomingViewingKey, start: DiversifierIndex) -> Self {
+        Addresses {
+            ivk,
+            next: Some(start),
+        }
+    }
+
+    /// Returns the diversifier index that the next call to [`Iterator::next`] will
+    /// yield, or `None` if the sequence is exhausted.
+    pub fn next_index(&self) -> Option<DiversifierIndex> {
+        self.next
+    }
+
+    fn advance(&mut self) -> Option<DiversifierIndex> {
+        let j = self.next?;
+        let mut next = j;
+        self.next = next.increment().ok().map(|()| next);
+        Some(j)
+    }
+
+    /// Advances to the first diversifier index for which `predicate` returns `true`,
+    /// checking at most `limit` indices, and returns that index with its address.
+    ///
+    /// The predicate is only given the index, so an address is derived just for the
+    /// index that is returned. This makes it cheap to skip a long run of indices that
+    /// a wallet has already handed out. Returns `None` if no index within `limit`
+    /// satisfies the predicate; the iterator is then positioned after the last index
+    /// checked.
+    pub fn find_next(
+        &mut self,
+        limit: usize,
+        mut predicate: impl FnMut(&DiversifierIndex) -> bool,
+    ) -> Option<(DiversifierIndex, Address)> {
+        for _ in 0..limit {
+            let j = self.advance()?;
+            if predicate(&j) {
+                return Some((j, self.ivk.address_at(j)));
+            }
+        }
+        None
+    }
+}
+
+impl Iterator for Addresses {
+    type Item = (DiversifierIndex, Address);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance().map(|j| (j, self.ivk.address_at(j)))
+    }
+}
+
+impl IncomingViewingKey {
+    /// Returns an iterator over the payment addresses for this key, starting at the
+    /// given diversifier index.
+    pub fn addresses_from(&self, start: impl Into<DiversifierIndex>) -> Addresses {
+        Addresses::new(self.clone(), start.into())
+    }
+}
+
+impl FullViewingKey {
+    /// Returns an iterator over the payment addresses for this key in the given scope,
+    /// starting at the given diversifier index.
+    ///
+    /// The incoming viewing key for the scope is derived once, rather than for each
+    /// address as [`FullViewingKey::address_at`] does.
+    pub fn addresses_from(&self, start: impl Into<DiversifierIndex>, scope: Scope) -> Addresses {
+        Addresses::new(self.to_ivk(scope), start.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{DiversifierIndex, FullViewingKey, Scope};
+    use crate::keys::SpendingKey;
+
+    fn fvk() -> FullViewingKey {
+        (&SpendingKey::from_bytes([7; 32]).unwrap()).into()
+    }
+
+    #[test]
+    fn addresses_match_address_at() {
+        let fvk = fvk();
+        for scope in [Scope::External, Scope::Internal] {
+            let addresses = fvk.addresses_from(5u32, scope).take(3).collect::<Vec<_>>();
+            for (k, (j, addr)) in addresses.into_iter().enumerate() {
+                assert_eq!(j, DiversifierIndex::from(5 + k as u32));
+                assert_eq!(addr, fvk.address_at(j, scope));
+                assert_eq!(fvk.to_ivk(scope).diversifier_index(&addr), Some(j));
+            }
+        }
+    }
+
+    #[test]
+    fn find_next_skips_used_indices() {
+        let fvk = fvk();
+        let used = [0u32, 1, 2, 4];
+        let mut addresses = fvk.addresses_from(0u32, Scope::External);
+
+        let (j, addr) = addresses
+            .find_next(10, |j| !used.contains(&u32::try_from(*j).unwrap()))
+            .unwrap();
+        assert_eq!(j, DiversifierIndex::from(3u32));
+        assert_eq!(addr, fvk.address_at(3u32, Scope::External));
+
+        // Rotation continues from after the returned index.
+        let (j, _) = addresses
+            .find_next(10, |j| !used.contains(&u32::try_from(*j).unwrap()))
+            .unwrap();
+        assert_eq!(j, DiversifierIndex::from(5u32));
+
+        // The search is bounded.
+        assert!(addresses.find_next(3, |_| false).is_none());
+        assert_eq!(addresses.next_index(), Some(DiversifierIndex::from(9u32)));
+    }
+
+    #[test]
+    fn sequence_ends_at_max_index() {
+        let max = DiversifierIndex::from([0xff; 11]);
+        let mut addresses = fvk().to_ivk(Scope::External).addresses_from(max);
+        assert_eq!(addresses.next().map(|(j, _)| j), Some(max));
+        assert!(addresses.next().is_none());
+        assert!(addresses.find_next(1, |_| true).is_none());
+    }
+}
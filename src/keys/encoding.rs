@@ -0,0 +1,548 @@
+//! Unified viewing key encodings of Orchard keys, as defined in [ZIP 316].
+//!
+//! A unified full viewing key (`uview`) or unified incoming viewing key (`uivk`) is a
+//! container of typed items: each item is its compactSize typecode, the compactSize length
+//! of its data, and the data, and the items are sorted by strictly increasing typecode. The
+//! items are followed by the human-readable part padded with zeros to 16 bytes, and the
+//! whole is F4Jumbled and encoded with Bech32m, without Bech32m's length limit.
+//!
+//! The encoders here produce Orchard-only containers. The decoders accept a container
+//! with other items, and return its Orchard component. Revision 0 of ZIP 316 is
+//! implemented; an item with a "MUST-understand" metadata typecode is rejected.
+//!
+//! There is no string encoding of an Orchard extended spending key here: ZIP 32 defines
+//! one for Sapling (`secret-extended-key-main`), but none for Orchard. The binary
+//! encoding is [`ExtendedSpendingKey::to_bytes`].
+//!
+//! [`ExtendedSpendingKey::to_bytes`]: crate::zip32::ExtendedSpendingKey::to_bytes
+//! [ZIP 316]: https://zips.z.cash/zip-0316
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use bech32::{primitives::decode::CheckedHrpstring, Bech32m, Checksum, Hrp};
+use blake2b_simd::Params;
+use corez::io::{self, Read};
+
+use super::{FullViewingKey, IncomingViewingKey};
+use crate::bundle::encoding::{read_compact_size, write_compact_size};
+
+/// The typecode of a P2PKH item.
+const TYPECODE_P2PKH: u32 = 0x00;
+/// The typecode of a P2SH item, which a viewing key cannot contain.
+const TYPECODE_P2SH: u32 = 0x01;
+/// The typecode of a Sapling item.
+const TYPECODE_SAPLING: u32 = 0x02;
+/// The typecode of an Orchard item.
+const TYPECODE_ORCHARD: u32 = 0x03;
+
+/// The typecodes of metadata items that a decoder must understand to accept a container.
+const MUST_UNDERSTAND_TYPECODES: core::ops::RangeInclusive<u32> = 0xE0..=0xFC;
+
+/// The length of the padding that follows the items.
+const PADDING_LEN: usize = 16;
+
+/// The minimum and maximum lengths of an F4Jumble input.
+const MIN_JUMBLE_LEN: usize = 48;
+const MAX_JUMBLE_LEN: usize = 4_194_368;
+
+/// The Bech32m checksum, with the code length extended for ZIP 316.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bech32mZip316 {}
+
+impl Checksum for Bech32mZip316 {
+    type MidstateRepr = <Bech32m as Checksum>::MidstateRepr;
+    const CODE_LENGTH: usize = MAX_JUMBLE_LEN;
+    const CHECKSUM_LENGTH: usize = Bech32m::CHECKSUM_LENGTH;
+    const GENERATOR_SH: [u32; 5] = Bech32m::GENERATOR_SH;
+    const TARGET_RESIDUE: u32 = Bech32m::TARGET_RESIDUE;
+}
+
+/// A Zcash network, which determines the human-readable part of an encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Network {
+    /// Zcash Mainnet.
+    Main,
+    /// Zcash Testnet.
+    Test,
+    /// A private regression-test network.
+    Regtest,
+}
+
+impl Network {
+    const ALL: [Network; 3] = [Network::Main, Network::Test, Network::Regtest];
+
+    /// Returns the human-readable part of a unified full viewing key on this network.
+    pub fn uview_hrp(self) -> &'static str {
+        match self {
+            Network::Main => "uview",
+            Network::Test => "uviewtest",
+            Network::Regtest => "uviewregtest",
+        }
+    }
+
+    /// Returns the human-readable part of a unified incoming viewing key on this network.
+    pub fn uivk_hrp(self) -> &'static str {
+        match self {
+            Network::Main => "uivk",
+            Network::Test => "uivktest",
+            Network::Regtest => "uivkregtest",
+        }
+    }
+}
+
+/// An error type for the kinds of errors that can occur while decoding a unified viewing
+/// key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnifiedKeyError {
+    /// The string is not a valid Bech32m encoding, or its padding bits are invalid.
+    InvalidBech32m,
+    /// The human-readable part is not the one for this kind of key on any network.
+    UnknownHrp(String),
+    /// The encoded data has the given length, which is out of range for F4Jumble.
+    InvalidLength(usize),
+    /// The padding that follows the items does not match the human-readable part.
+    InvalidPadding,
+    /// An item is truncated, or its typecode or length is not a canonical compactSize.
+    MalformedItem,
+    /// The items are not in strictly increasing typecode order.
+    ItemsOutOfOrder,
+    /// The container has an item with the given typecode, which it cannot contain or which
+    /// this decoder does not understand.
+    UnsupportedTypecode(u32),
+    /// The item with the given typecode has an invalid length for that typecode.
+    InvalidItemLength(u32),
+    /// The container has no Orchard item.
+    MissingOrchardItem,
+    /// The Orchard item is not a valid key.
+    InvalidOrchardItem,
+}
+
+impl fmt::Display for UnifiedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnifiedKeyError::InvalidBech32m => write!(f, "Invalid Bech32m encoding"),
+            UnifiedKeyError::UnknownHrp(hrp) => write!(f, "Unknown human-readable part {hrp}"),
+            UnifiedKeyError::InvalidLength(len) => {
+                write!(f, "Encoded data has invalid length {len}")
+            }
+            UnifiedKeyError::InvalidPadding => write!(f, "Invalid padding"),
+            UnifiedKeyError::MalformedItem => write!(f, "Malformed item"),
+            UnifiedKeyError::ItemsOutOfOrder => write!(f, "Items are not in typecode order"),
+            UnifiedKeyError::UnsupportedTypecode(typecode) => {
+                write!(f, "Unsupported typecode {typecode:#x}")
+            }
+            UnifiedKeyError::InvalidItemLength(typecode) => {
+                write!(f, "Invalid length for the item with typecode {typecode:#x}")
+            }
+            UnifiedKeyError::MissingOrchardItem => write!(f, "Missing Orchard item"),
+            UnifiedKeyError::InvalidOrchardItem => write!(f, "Invalid Orchard item"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnifiedKeyError {}
+
+/// The output of `H_i` in F4Jumble, of the length of the left half.
+fn f4jumble_h(i: u8, u: &[u8], len: usize) -> blake2b_simd::Hash {
+    let mut personal = [0; 16];
+    personal[..13].copy_from_slice(b"UA_F4Jumble_H");
+    personal[13] = i;
+    Params::new().hash_length(len).personal(&personal).hash(u)
+}
+
+/// XORs the output of `G_i` in F4Jumble into `out`.
+fn xor_f4jumble_g(i: u8, u: &[u8], out: &mut [u8]) {
+    let mut personal = [0; 16];
+    personal[..13].copy_from_slice(b"UA_F4Jumble_G");
+    personal[13] = i;
+    for (j, chunk) in out.chunks_mut(64).enumerate() {
+        personal[14..].copy_from_slice(&(j as u16).to_le_bytes());
+        let hash = Params::new().hash_length(64).personal(&personal).hash(u);
+        xor(chunk, hash.as_bytes());
+    }
+}
+
+fn xor(out: &mut [u8], mask: &[u8]) {
+    for (o, m) in out.iter_mut().zip(mask) {
+        *o ^= m;
+    }
+}
+
+/// Applies the F4Jumble permutation, or its inverse, in place.
+///
+/// `message` must have a length between [`MIN_JUMBLE_LEN`] and [`MAX_JUMBLE_LEN`].
+fn f4jumble(message: &mut [u8], inverse: bool) {
+    let left_len = core::cmp::min(64, message.len() / 2);
+    let (left, right) = message.split_at_mut(left_len);
+    let round_g = |i, left: &mut [u8], right: &mut [u8]| xor_f4jumble_g(i, left, right);
+    let round_h =
+        |i, left: &mut [u8], right: &mut [u8]| xor(left, f4jumble_h(i, right, left_len).as_bytes());
+
+    if inverse {
+        round_h(1, left, right);
+        round_g(1, left, right);
+        round_h(0, left, right);
+        round_g(0, left, right);
+    } else {
+        round_g(0, left, right);
+        round_h(0, left, right);
+        round_g(1, left, right);
+        round_h(1, left, right);
+    }
+}
+
+fn padding(hrp: &str) -> [u8; PADDING_LEN] {
+    let mut padding = [0; PADDING_LEN];
+    padding[..hrp.len()].copy_from_slice(hrp.as_bytes());
+    padding
+}
+
+/// Encodes a container holding only an Orchard item.
+fn encode_orchard_only(hrp: &'static str, item: &[u8]) -> String {
+    let mut data = Vec::new();
+    write_compact_size(&mut data, TYPECODE_ORCHARD as usize).expect("writing to a Vec");
+    write_compact_size(&mut data, item.len()).expect("writing to a Vec");
+    data.extend_from_slice(item);
+    data.extend_from_slice(&padding(hrp));
+    f4jumble(&mut data, false);
+
+    bech32::encode::<Bech32mZip316>(Hrp::parse_unchecked(hrp), &data)
+        .expect("the length is within the code length")
+}
+
+/// Decodes a viewing key container whose human-readable part is given by `hrp` for some
+/// network, and returns the network and the data of its Orchard item.
+///
+/// The container's Sapling and Orchard items must have the given lengths. Items with
+/// unknown typecodes are ignored.
+fn decode_orchard_item(
+    encoding: &str,
+    hrp: fn(Network) -> &'static str,
+    sapling_len: usize,
+    orchard_len: usize,
+) -> Result<(Network, Vec<u8>), UnifiedKeyError> {
+    let parsed = CheckedHrpstring::new::<Bech32mZip316>(encoding)
+        .map_err(|_| UnifiedKeyError::InvalidBech32m)?;
+    parsed
+        .validate_segwit_padding()
+        .map_err(|_| UnifiedKeyError::InvalidBech32m)?;
+    let found = parsed.hrp().to_lowercase();
+    let network = Network::ALL
+        .into_iter()
+        .find(|network| hrp(*network) == found)
+        .ok_or(UnifiedKeyError::UnknownHrp(found))?;
+
+    let mut data: Vec<u8> = parsed.byte_iter().collect();
+    if !(MIN_JUMBLE_LEN..=MAX_JUMBLE_LEN).contains(&data.len()) {
+        return Err(UnifiedKeyError::InvalidLength(data.len()));
+    }
+    f4jumble(&mut data, true);
+    let (mut items, trailer) = data.split_at(data.len() - PADDING_LEN);
+    if trailer != padding(hrp(network)) {
+        return Err(UnifiedKeyError::InvalidPadding);
+    }
+
+    let mut orchard = None;
+    let mut prev_typecode = None;
+    while !items.is_empty() {
+        let read_item = |items: &mut &[u8]| -> io::Result<(u32, Vec<u8>)> {
+            let typecode = read_compact_size(&mut *items)?;
+            let len = read_compact_size(&mut *items)?;
+            // Check the length before allocating, since it is untrusted.
+            if len > items.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "item data is truncated",
+                ));
+            }
+            let mut item = alloc::vec![0; len];
+            items.read_exact(&mut item)?;
+            Ok((typecode as u32, item))
+        };
+        let (typecode, item) = read_item(&mut items).map_err(|_| UnifiedKeyError::MalformedItem)?;
+
+        if prev_typecode.is_some_and(|prev| prev >= typecode) {
+            return Err(UnifiedKeyError::ItemsOutOfOrder);
+        }
+        prev_typecode = Some(typecode);
+
+        let expected_len = match typecode {
+            TYPECODE_P2SH => return Err(UnifiedKeyError::UnsupportedTypecode(typecode)),
+            t if MUST_UNDERSTAND_TYPECODES.contains(&t) => {
+                return Err(UnifiedKeyError::UnsupportedTypecode(typecode))
+            }
+            TYPECODE_P2PKH => Some(65),
+            TYPECODE_SAPLING => Some(sapling_len),
+            TYPECODE_ORCHARD => Some(orchard_len),
+            _ => None,
+        };
+        if expected_len.is_some_and(|len| len != item.len()) {
+            return Err(UnifiedKeyError::InvalidItemLength(typecode));
+        }
+        if typecode == TYPECODE_ORCHARD {
+            orchard = Some(item);
+        }
+    }
+
+    orchard
+        .map(|item| (network, item))
+        .ok_or(UnifiedKeyError::MissingOrchardItem)
+}
+
+impl FullViewingKey {
+    /// Encodes this key as an Orchard-only unified full viewing key for `network`.
+    pub fn to_uview(&self, network: Network) -> String {
+        encode_orchard_only(network.uview_hrp(), &self.to_bytes())
+    }
+
+    /// Decodes the Orchard component of a unified full viewing key, and returns it with
+    /// the network that the key is for.
+    pub fn from_uview(encoding: &str) -> Result<(Network, Self), UnifiedKeyError> {
+        let (network, item) = decode_orchard_item(encoding, Network::uview_hrp, 128, 96)?;
+        let fvk = FullViewingKey::from_bytes(&item.try_into().expect("length was checked"))
+            .ok_or(UnifiedKeyError::InvalidOrchardItem)?;
+        Ok((network, fvk))
+    }
+}
+
+impl IncomingViewingKey {
+    /// Encodes this key as an Orchard-only unified incoming viewing key for `network`.
+    pub fn to_uivk(&self, network: Network) -> String {
+        encode_orchard_only(network.uivk_hrp(), &self.to_bytes())
+    }
+
+    /// Decodes the Orchard component of a unified incoming viewing key, and returns it
+    /// with the network that the key is for.
+    pub fn from_uivk(encoding: &str) -> Result<(Network, Self), UnifiedKeyError> {
+        let (network, item) = decode_orchard_item(encoding, Network::uivk_hrp, 64, 64)?;
+        let ivk = IncomingViewingKey::from_bytes(&item.try_into().expect("length was checked"))
+            .into_option()
+            .ok_or(UnifiedKeyError::InvalidOrchardItem)?;
+        Ok((network, ivk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use bech32::Hrp;
+    use rand::{rngs::OsRng, RngCore};
+
+    use super::{
+        f4jumble, padding, Bech32mZip316, Network, UnifiedKeyError, TYPECODE_ORCHARD,
+        TYPECODE_P2PKH, TYPECODE_P2SH, TYPECODE_SAPLING,
+    };
+    use crate::{
+        bundle::encoding::write_compact_size,
+        keys::{FullViewingKey, IncomingViewingKey, Scope, SpendingKey},
+    };
+
+    /// Encodes a container of the given raw items, padded with `padding_hrp`.
+    fn encode_items(hrp: &str, items: &[(u32, &[u8])], padding_hrp: &str) -> String {
+        let mut data = Vec::new();
+        for (typecode, item) in items {
+            write_compact_size(&mut data, *typecode as usize).unwrap();
+            write_compact_size(&mut data, item.len()).unwrap();
+            data.extend_from_slice(item);
+        }
+        data.extend_from_slice(&padding(padding_hrp));
+        f4jumble(&mut data, false);
+        bech32::encode::<Bech32mZip316>(Hrp::parse(hrp).unwrap(), &data).unwrap()
+    }
+
+    fn fvk() -> FullViewingKey {
+        FullViewingKey::from(&SpendingKey::random(&mut OsRng))
+    }
+
+    #[test]
+    fn f4jumble_is_invertible() {
+        for len in [48, 127, 128, 129, 200, 4_000] {
+            let mut message = alloc::vec![0; len];
+            OsRng.fill_bytes(&mut message);
+            let mut jumbled = message.clone();
+            f4jumble(&mut jumbled, false);
+            assert_ne!(jumbled, message);
+            f4jumble(&mut jumbled, true);
+            assert_eq!(jumbled, message);
+        }
+    }
+
+    #[test]
+    fn f4jumble_test_vectors() {
+        for tv in crate::test_vectors::f4jumble::TEST_VECTORS {
+            let mut message = tv.normal.to_vec();
+            f4jumble(&mut message, false);
+            assert_eq!(message, tv.jumbled);
+            f4jumble(&mut message, true);
+            assert_eq!(message, tv.normal);
+        }
+    }
+
+    #[test]
+    fn viewing_keys_round_trip() {
+        let fvk = fvk();
+        let ivk = fvk.to_ivk(Scope::External);
+        for network in [Network::Main, Network::Test, Network::Regtest] {
+            let uview = fvk.to_uview(network);
+            assert!(uview.starts_with(&(String::from(network.uview_hrp()) + "1")));
+            let (decoded_network, decoded) = FullViewingKey::from_uview(&uview).unwrap();
+            assert_eq!((decoded_network, decoded), (network, fvk.clone()));
+            // Bech32m also permits an all-uppercase encoding.
+            assert_eq!(
+                FullViewingKey::from_uview(&uview.to_uppercase()).unwrap().1,
+                fvk
+            );
+
+            let uivk = ivk.to_uivk(network);
+            let (decoded_network, decoded) = IncomingViewingKey::from_uivk(&uivk).unwrap();
+            assert_eq!((decoded_network, decoded), (network, ivk.clone()));
+
+            assert_eq!(
+                IncomingViewingKey::from_uivk(&uview),
+                Err(UnifiedKeyError::UnknownHrp(network.uview_hrp().into()))
+            );
+            assert_eq!(
+                FullViewingKey::from_uview(&uivk),
+                Err(UnifiedKeyError::UnknownHrp(network.uivk_hrp().into()))
+            );
+        }
+    }
+
+    #[test]
+    fn other_items_are_skipped() {
+        let fvk = fvk();
+        let orchard = fvk.to_bytes();
+        let uview = encode_items(
+            "uviewtest",
+            &[
+                (TYPECODE_P2PKH, &[1; 65]),
+                (TYPECODE_SAPLING, &[2; 128]),
+                (TYPECODE_ORCHARD, &orchard),
+                (0xC0, &[3; 5]),
+                (0xFFFA, &[]),
+            ],
+            "uviewtest",
+        );
+        assert_eq!(FullViewingKey::from_uview(&uview), Ok((Network::Test, fvk)));
+    }
+
+    #[test]
+    fn invalid_encodings_are_rejected() {
+        let fvk = fvk();
+        let orchard = fvk.to_bytes();
+        let decode = |s: &str| FullViewingKey::from_uview(s);
+
+        // Corrupted checksum, and mixed case.
+        let mut uview = fvk.to_uview(Network::Main).into_bytes();
+        let last = uview.len() - 1;
+        uview[last] = if uview[last] == b'q' { b'p' } else { b'q' };
+        assert_eq!(
+            decode(core::str::from_utf8(&uview).unwrap()),
+            Err(UnifiedKeyError::InvalidBech32m)
+        );
+        let mut mixed = fvk.to_uview(Network::Main);
+        mixed.replace_range(..1, "U");
+        assert_eq!(decode(&mixed), Err(UnifiedKeyError::InvalidBech32m));
+
+        // A Bech32 (not Bech32m) checksum.
+        let mut data = Vec::new();
+        write_compact_size(&mut data, TYPECODE_ORCHARD as usize).unwrap();
+        write_compact_size(&mut data, orchard.len()).unwrap();
+        data.extend_from_slice(&orchard);
+        data.extend_from_slice(&padding("uview"));
+        f4jumble(&mut data, false);
+        let bech32 = bech32::encode::<bech32::Bech32>(Hrp::parse("uview").unwrap(), &data).unwrap();
+        assert_eq!(decode(&bech32), Err(UnifiedKeyError::InvalidBech32m));
+
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_ORCHARD, &orchard)],
+                "uviewtest"
+            )),
+            Err(UnifiedKeyError::InvalidPadding)
+        );
+        assert_eq!(
+            decode(&encode_items("uview", &[(0xFFFA, &[0; 30])], "uview")),
+            Err(UnifiedKeyError::MissingOrchardItem)
+        );
+        assert_eq!(
+            decode(&encode_items("uview", &[(0xFFFA, &[0; 8])], "uview")),
+            Err(UnifiedKeyError::InvalidLength(28))
+        );
+        // An item whose length exceeds the remaining data is rejected before its data
+        // is allocated.
+        let mut data = Vec::new();
+        write_compact_size(&mut data, 0xFFFA).unwrap();
+        write_compact_size(&mut data, 0x0200_0000).unwrap();
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&padding("uview"));
+        f4jumble(&mut data, false);
+        let truncated = bech32::encode::<Bech32mZip316>(Hrp::parse("uview").unwrap(), &data);
+        assert_eq!(
+            decode(&truncated.unwrap()),
+            Err(UnifiedKeyError::MalformedItem)
+        );
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_ORCHARD, &orchard), (TYPECODE_SAPLING, &[2; 128])],
+                "uview"
+            )),
+            Err(UnifiedKeyError::ItemsOutOfOrder)
+        );
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_ORCHARD, &orchard), (TYPECODE_ORCHARD, &orchard)],
+                "uview"
+            )),
+            Err(UnifiedKeyError::ItemsOutOfOrder)
+        );
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_P2SH, &[1; 20]), (TYPECODE_ORCHARD, &orchard)],
+                "uview"
+            )),
+            Err(UnifiedKeyError::UnsupportedTypecode(TYPECODE_P2SH))
+        );
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_ORCHARD, &orchard), (0xE0, &[])],
+                "uview"
+            )),
+            Err(UnifiedKeyError::UnsupportedTypecode(0xE0))
+        );
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_ORCHARD, &orchard[..64])],
+                "uview"
+            )),
+            Err(UnifiedKeyError::InvalidItemLength(TYPECODE_ORCHARD))
+        );
+        assert_eq!(
+            decode(&encode_items(
+                "uview",
+                &[(TYPECODE_ORCHARD, &[0xFF; 96])],
+                "uview"
+            )),
+            Err(UnifiedKeyError::InvalidOrchardItem)
+        );
+
+        // A non-canonical compactSize typecode.
+        let mut data = alloc::vec![253, TYPECODE_ORCHARD as u8, 0, 96];
+        data.extend_from_slice(&orchard);
+        data.extend_from_slice(&padding("uview"));
+        f4jumble(&mut data, false);
+        let non_canonical =
+            bech32::encode::<Bech32mZip316>(Hrp::parse("uview").unwrap(), &data).unwrap();
+        assert_eq!(decode(&non_canonical), Err(UnifiedKeyError::MalformedItem));
+    }
+}
@@ -36,6 +36,7 @@ mod constants;
 #[cfg(feature = "unstable-voting-circuits")]
 pub mod constants;
 pub mod keys;
+pub mod memo;
 pub mod note;
 pub mod note_encryption;
 pub mod pczt;
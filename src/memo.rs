@@ -0,0 +1,302 @@
+//! Structured memos, as defined in [ZIP 302].
+//!
+//! Every Orchard output carries a 512-byte memo field. The builder accepts either a
+//! [`Memo`] or the raw field. Decryption results carry the field as [`MemoBytes`], which
+//! keeps the raw field (because a field that claims to hold text may not be valid UTF-8)
+//! and parses it on request with [`MemoBytes::parse`].
+//!
+//! [ZIP 302]: https://zips.z.cash/zip-0302
+
+use alloc::{boxed::Box, string::String};
+use core::{fmt, ops::Deref, str};
+
+/// The length of a memo field in bytes.
+pub const MEMO_SIZE: usize = 512;
+
+/// An error type for the kinds of errors that can occur while parsing or constructing a
+/// memo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemoError {
+    /// The memo field indicates UTF-8 text, but its contents are not valid UTF-8.
+    InvalidUtf8(str::Utf8Error),
+    /// The text has the given length in bytes, which does not fit in a memo field.
+    TooLong(usize),
+}
+
+impl fmt::Display for MemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoError::InvalidUtf8(e) => write!(f, "Memo text is not valid UTF-8: {e}"),
+            MemoError::TooLong(len) => write!(
+                f,
+                "Memo text is {len} bytes long, but at most {MEMO_SIZE} bytes fit"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MemoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MemoError::InvalidUtf8(e) => Some(e),
+            MemoError::TooLong(_) => None,
+        }
+    }
+}
+
+/// The text of a [`Memo::Text`] memo: a string of at most [`MEMO_SIZE`] bytes, without
+/// trailing zero bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextMemo(String);
+
+impl TextMemo {
+    /// Constructs a text memo, trimming any trailing zero bytes from `text`.
+    ///
+    /// Returns [`MemoError::TooLong`] if the trimmed text is longer than [`MEMO_SIZE`]
+    /// bytes.
+    pub fn new(text: &str) -> Result<Self, MemoError> {
+        let text = text.trim_end_matches('\0');
+        if text.len() > MEMO_SIZE {
+            Err(MemoError::TooLong(text.len()))
+        } else {
+            Ok(TextMemo(text.into()))
+        }
+    }
+}
+
+impl Deref for TextMemo {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A memo field, interpreted as specified in [ZIP 302].
+///
+/// [ZIP 302]: https://zips.z.cash/zip-0302
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Memo {
+    /// The memo field indicates that there is no memo (`0xF6` followed by zero bytes).
+    Empty,
+    /// UTF-8 text, padded with zero bytes.
+    ///
+    /// A memo field whose first byte is at most `0xF4` holds text. Trailing zero bytes
+    /// are padding, and are not part of the text.
+    Text(TextMemo),
+    /// Arbitrary data, following the `0xFF` first byte.
+    Arbitrary(Box<[u8; MEMO_SIZE - 1]>),
+    /// A memo field in a format that is reserved for future use, kept as its encoding.
+    ///
+    /// This includes a field that starts with `0xF6` but is not otherwise zero.
+    Future(Box<[u8; MEMO_SIZE]>),
+}
+
+impl Memo {
+    /// Parses a memo field.
+    ///
+    /// Returns [`MemoError::InvalidUtf8`] if the field indicates text but is not valid
+    /// UTF-8.
+    pub fn from_bytes(bytes: &[u8; MEMO_SIZE]) -> Result<Self, MemoError> {
+        match bytes[0] {
+            0x00..=0xF4 => {
+                let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+                str::from_utf8(&bytes[..len])
+                    .map(|text| Memo::Text(TextMemo(text.into())))
+                    .map_err(MemoError::InvalidUtf8)
+            }
+            0xF6 if bytes[1..].iter().all(|b| *b == 0) => Ok(Memo::Empty),
+            0xFF => Ok(Memo::Arbitrary(Box::new(
+                bytes[1..].try_into().expect("correct length"),
+            ))),
+            _ => Ok(Memo::Future(Box::new(*bytes))),
+        }
+    }
+
+    /// Encodes this memo as a memo field.
+    pub fn to_bytes(&self) -> [u8; MEMO_SIZE] {
+        let mut bytes = [0; MEMO_SIZE];
+        match self {
+            Memo::Empty => bytes[0] = 0xF6,
+            // Valid UTF-8 never starts with a byte above 0xF4.
+            Memo::Text(text) => bytes[..text.len()].copy_from_slice(text.as_bytes()),
+            Memo::Arbitrary(data) => {
+                bytes[0] = 0xFF;
+                bytes[1..].copy_from_slice(&data[..]);
+            }
+            Memo::Future(encoding) => bytes = **encoding,
+        }
+        bytes
+    }
+}
+
+impl str::FromStr for Memo {
+    type Err = MemoError;
+
+    /// Constructs a text memo, or [`Memo::Empty`] if `text` is empty.
+    fn from_str(text: &str) -> Result<Self, MemoError> {
+        if text.is_empty() {
+            Ok(Memo::Empty)
+        } else {
+            TextMemo::new(text).map(Memo::Text)
+        }
+    }
+}
+
+impl TryFrom<[u8; MEMO_SIZE]> for Memo {
+    type Error = MemoError;
+
+    fn try_from(bytes: [u8; MEMO_SIZE]) -> Result<Self, MemoError> {
+        Memo::from_bytes(&bytes)
+    }
+}
+
+impl TryFrom<&[u8; MEMO_SIZE]> for Memo {
+    type Error = MemoError;
+
+    fn try_from(bytes: &[u8; MEMO_SIZE]) -> Result<Self, MemoError> {
+        Memo::from_bytes(bytes)
+    }
+}
+
+impl From<Memo> for [u8; MEMO_SIZE] {
+    fn from(memo: Memo) -> Self {
+        memo.to_bytes()
+    }
+}
+
+impl From<&Memo> for [u8; MEMO_SIZE] {
+    fn from(memo: &Memo) -> Self {
+        memo.to_bytes()
+    }
+}
+
+/// A memo field as decrypted from an output, before it is parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoBytes([u8; MEMO_SIZE]);
+
+impl MemoBytes {
+    /// Returns the raw memo field.
+    pub fn as_bytes(&self) -> &[u8; MEMO_SIZE] {
+        &self.0
+    }
+
+    /// Parses the memo field.
+    ///
+    /// Returns [`MemoError::InvalidUtf8`] if the field indicates text but is not valid
+    /// UTF-8; the field is still available from [`MemoBytes::as_bytes`].
+    pub fn parse(&self) -> Result<Memo, MemoError> {
+        Memo::from_bytes(&self.0)
+    }
+}
+
+impl From<[u8; MEMO_SIZE]> for MemoBytes {
+    fn from(bytes: [u8; MEMO_SIZE]) -> Self {
+        MemoBytes(bytes)
+    }
+}
+
+impl From<MemoBytes> for [u8; MEMO_SIZE] {
+    fn from(memo: MemoBytes) -> Self {
+        memo.0
+    }
+}
+
+impl From<&MemoBytes> for [u8; MEMO_SIZE] {
+    fn from(memo: &MemoBytes) -> Self {
+        memo.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::{Memo, MemoBytes, MemoError, TextMemo, MEMO_SIZE};
+
+    fn field(prefix: &[u8]) -> [u8; MEMO_SIZE] {
+        let mut bytes = [0; MEMO_SIZE];
+        bytes[..prefix.len()].copy_from_slice(prefix);
+        bytes
+    }
+
+    #[test]
+    fn parses_zip_302_formats() {
+        assert_eq!(Memo::try_from(field(&[0xF6])), Ok(Memo::Empty));
+        assert_eq!(
+            Memo::try_from(field(b"Thank you for your purchase")),
+            Ok(Memo::Text(
+                TextMemo::new("Thank you for your purchase").unwrap()
+            ))
+        );
+        // An all-zero field is empty text, not an empty memo.
+        assert_eq!(
+            Memo::try_from([0; MEMO_SIZE]),
+            Ok(Memo::Text(TextMemo::new("").unwrap()))
+        );
+        // Only trailing zero bytes are padding.
+        let text = Memo::try_from(field(b"a\0b")).unwrap();
+        assert!(matches!(&text, Memo::Text(text) if &**text == "a\0b"));
+        let full = [b'z'; MEMO_SIZE];
+        assert!(matches!(Memo::try_from(full), Ok(Memo::Text(text)) if text.len() == MEMO_SIZE));
+
+        assert!(matches!(
+            Memo::try_from(field(&[0xC3, 0x28])),
+            Err(MemoError::InvalidUtf8(_))
+        ));
+
+        let mut arbitrary = [0xAB; MEMO_SIZE];
+        arbitrary[0] = 0xFF;
+        assert_eq!(
+            Memo::try_from(arbitrary),
+            Ok(Memo::Arbitrary(Box::new([0xAB; MEMO_SIZE - 1])))
+        );
+
+        for prefix in [&[0xF5][..], &[0xF6, 0x01], &[0xF7], &[0xFE]] {
+            assert_eq!(
+                Memo::try_from(field(prefix)),
+                Ok(Memo::Future(Box::new(field(prefix))))
+            );
+        }
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let mut arbitrary = [7; MEMO_SIZE];
+        arbitrary[0] = 0xFF;
+        for bytes in [
+            field(&[0xF6]),
+            field("\u{1F980} memo".as_bytes()),
+            [0; MEMO_SIZE],
+            arbitrary,
+            field(&[0xF6, 0, 0, 1]),
+            field(&[0xF9, 1]),
+        ] {
+            let memo = Memo::try_from(&bytes).unwrap();
+            assert_eq!(<[u8; MEMO_SIZE]>::from(&memo), bytes);
+            assert_eq!(<[u8; MEMO_SIZE]>::from(memo), bytes);
+        }
+    }
+
+    #[test]
+    fn memo_bytes_keep_unparseable_fields() {
+        let text = MemoBytes::from(field(b"Hello"));
+        assert_eq!(text.parse(), Ok("Hello".parse().unwrap()));
+        assert_eq!(<[u8; MEMO_SIZE]>::from(text), field(b"Hello"));
+
+        let invalid = MemoBytes::from(field(&[0xC3, 0x28]));
+        assert!(matches!(invalid.parse(), Err(MemoError::InvalidUtf8(_))));
+        assert_eq!(invalid.as_bytes(), &field(&[0xC3, 0x28]));
+    }
+
+    #[test]
+    fn text_memos_are_bounded() {
+        assert_eq!("".parse::<Memo>(), Ok(Memo::Empty));
+        assert_eq!("hi\0\0".parse::<Memo>().unwrap().to_bytes(), field(b"hi"));
+        let long = "x".repeat(MEMO_SIZE + 1);
+        assert_eq!(long.parse::<Memo>(), Err(MemoError::TooLong(MEMO_SIZE + 1)));
+        assert!(long[1..].parse::<Memo>().is_ok());
+    }
+}
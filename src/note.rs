@@ -66,7 +66,7 @@ impl NoteVersion {
 pub(crate) mod nullifier;
 #[cfg(feature = "unstable-voting-circuits")]
 pub mod nullifier;
-pub use self::nullifier::Nullifier;
+pub use self::nullifier::{DetectedSpend, Nullifier, NullifierIndex};
 
 /// The randomness used to construct a note.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
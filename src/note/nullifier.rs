@@ -5,6 +5,11 @@
 //! nullifier-deriving key `nk`, the note's ψ randomness, and the note
 //! commitment, in a way that keeps them unlinkable without the viewing key
 //! but uniquely tied to the note.
+//!
+//! A wallet detects when its notes are spent by matching the nullifiers revealed by
+//! later actions against a [`NullifierIndex`] of its notes' nullifiers.
+
+use alloc::{collections::BTreeMap, vec::Vec};
 
 use group::{ff::PrimeField, Group};
 use memuse::DynamicUsage;
@@ -12,9 +17,11 @@ use pasta_curves::{arithmetic::CurveExt, pallas};
 use rand::RngCore;
 use subtle::{ConstantTimeEq, CtOption};
 
-use super::NoteCommitment;
+use super::{Note, NoteCommitment};
 use crate::{
-    keys::NullifierDerivingKey,
+    bundle::{Authorization, Bundle},
+    keys::{FullViewingKey, NullifierDerivingKey},
+    note_encryption::CompactAction,
     spec::{extract_p, mod_r_p},
 };
 
@@ -87,6 +94,191 @@ impl ConstantTimeEq for Nullifier {
     }
 }
 
+/// A wallet note whose nullifier is tracked by a [`NullifierIndex`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct IndexedNote<R> {
+    note_ref: R,
+    received_height: u32,
+    spent_height: Option<u32>,
+}
+
+/// A spend of a wallet note, detected by a [`NullifierIndex`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetectedSpend<R> {
+    nullifier: Nullifier,
+    note_ref: R,
+    action_idx: usize,
+}
+
+impl<R> DetectedSpend<R> {
+    /// Returns the nullifier revealed by the spend.
+    pub fn nullifier(&self) -> Nullifier {
+        self.nullifier
+    }
+
+    /// Returns the wallet's reference to the spent note.
+    pub fn note_ref(&self) -> &R {
+        &self.note_ref
+    }
+
+    /// Returns the index of the spending action within its bundle or list of compact
+    /// actions.
+    pub fn action_idx(&self) -> usize {
+        self.action_idx
+    }
+}
+
+/// An index of the nullifiers of a wallet's notes, used to detect when they are spent.
+///
+/// The wallet inserts the nullifier of each note it receives (see
+/// [`NullifierIndex::insert_note`]), along with its own reference `R` to the note and the
+/// height of the block that created it. It then ingests the actions of each later block
+/// (including the rest of the receiving block), and is told which of its notes each block
+/// spent, and in which action. Notes and spends can be rolled back to an earlier block
+/// height after a chain reorganization.
+#[derive(Clone, Debug)]
+pub struct NullifierIndex<R> {
+    notes: BTreeMap<Nullifier, IndexedNote<R>>,
+}
+
+impl<R> Default for NullifierIndex<R> {
+    fn default() -> Self {
+        NullifierIndex {
+            notes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<R: Clone> NullifierIndex<R> {
+    /// Constructs an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of notes in the index, spent or unspent.
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Returns `true` if the index contains no notes.
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// Inserts the nullifier of a note received in the block at `height`.
+    ///
+    /// Returns the previous reference for `nullifier`, if it was already present.
+    pub fn insert(&mut self, nullifier: Nullifier, note_ref: R, height: u32) -> Option<R> {
+        self.notes
+            .insert(
+                nullifier,
+                IndexedNote {
+                    note_ref,
+                    received_height: height,
+                    spent_height: None,
+                },
+            )
+            .map(|note| note.note_ref)
+    }
+
+    /// Inserts a note received in the block at `height`, computing its nullifier with
+    /// `fvk`, and returns the nullifier.
+    pub fn insert_note(
+        &mut self,
+        note: &Note,
+        fvk: &FullViewingKey,
+        note_ref: R,
+        height: u32,
+    ) -> Nullifier {
+        let nullifier = note.nullifier(fvk);
+        self.insert(nullifier, note_ref, height);
+        nullifier
+    }
+
+    /// Returns the wallet's reference to the note with the given nullifier, and whether
+    /// that note has been spent.
+    pub fn get(&self, nullifier: &Nullifier) -> Option<(&R, bool)> {
+        self.notes
+            .get(nullifier)
+            .map(|note| (&note.note_ref, note.spent_height.is_some()))
+    }
+
+    /// Returns an iterator over the nullifiers and references of the unspent notes.
+    pub fn unspent(&self) -> impl Iterator<Item = (&Nullifier, &R)> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| note.spent_height.is_none())
+            .map(|(nullifier, note)| (nullifier, &note.note_ref))
+    }
+
+    /// Marks as spent every unspent note whose nullifier is revealed by an action of
+    /// `bundle`, which was mined in the block at `height`.
+    ///
+    /// Returns the detected spends in action order.
+    pub fn ingest_bundle<T: Authorization, V>(
+        &mut self,
+        height: u32,
+        bundle: &Bundle<T, V>,
+    ) -> Vec<DetectedSpend<R>> {
+        self.ingest_nullifiers(height, bundle.actions().iter().map(|a| *a.nullifier()))
+    }
+
+    /// Marks as spent every unspent note whose nullifier is revealed by one of the
+    /// `actions` (the compact actions of one transaction), which were mined in the block
+    /// at `height`.
+    ///
+    /// Returns the detected spends in action order.
+    pub fn ingest_compact_actions(
+        &mut self,
+        height: u32,
+        actions: &[CompactAction],
+    ) -> Vec<DetectedSpend<R>> {
+        self.ingest_nullifiers(height, actions.iter().map(CompactAction::nullifier))
+    }
+
+    fn ingest_nullifiers(
+        &mut self,
+        height: u32,
+        nullifiers: impl Iterator<Item = Nullifier>,
+    ) -> Vec<DetectedSpend<R>> {
+        nullifiers
+            .enumerate()
+            .filter_map(|(action_idx, nullifier)| {
+                let note = self.notes.get_mut(&nullifier)?;
+                if note.spent_height.is_some() {
+                    return None;
+                }
+                note.spent_height = Some(height);
+                Some(DetectedSpend {
+                    nullifier,
+                    note_ref: note.note_ref.clone(),
+                    action_idx,
+                })
+            })
+            .collect()
+    }
+
+    /// Rolls the index back to the end of the block at `height`, removing the notes
+    /// received, and unmarking the spends detected, in later blocks.
+    pub fn rollback(&mut self, height: u32) {
+        self.notes.retain(|_, note| note.received_height <= height);
+        for note in self.notes.values_mut() {
+            if note.spent_height > Some(height) {
+                note.spent_height = None;
+            }
+        }
+    }
+
+    /// Removes the notes that were spent at or below `height`, once that block can no
+    /// longer be rolled back.
+    pub fn prune_spent(&mut self, height: u32) {
+        self.notes.retain(|_, note| {
+            note.spent_height
+                .is_none_or(|spent_height| spent_height > height)
+        });
+    }
+}
+
 /// Generators for property testing.
 #[cfg(any(test, feature = "test-dependencies"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "test-dependencies")))]
@@ -109,3 +301,89 @@ pub mod testing {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use proptest::prelude::*;
+    use zcash_note_encryption::EphemeralKeyBytes;
+
+    use super::{testing::arb_nullifier, NullifierIndex};
+    use crate::{
+        bundle::testing::arb_bundle,
+        keys::{FullViewingKey, SpendingKey},
+        note::{testing::arb_note, ExtractedNoteCommitment, NoteVersion},
+        note_encryption::CompactAction,
+        value::NoteValue,
+    };
+
+    proptest! {
+        #[test]
+        fn bundle_spends_are_detected(
+            bundle in arb_bundle(4),
+            foreign in arb_nullifier(),
+        ) {
+            let mut index = NullifierIndex::new();
+            let nullifiers: Vec<_> = bundle.actions().iter().map(|a| *a.nullifier()).collect();
+            index.insert(nullifiers[1], "b", 10);
+            index.insert(nullifiers[3], "d", 11);
+            index.insert(foreign, "x", 11);
+
+            let spends = index.ingest_bundle(12, &bundle);
+            prop_assert_eq!(
+                spends
+                    .iter()
+                    .map(|spend| (spend.nullifier(), *spend.note_ref(), spend.action_idx()))
+                    .collect::<Vec<_>>(),
+                vec![(nullifiers[1], "b", 1), (nullifiers[3], "d", 3)]
+            );
+            prop_assert_eq!(index.get(&nullifiers[1]), Some((&"b", true)));
+            prop_assert_eq!(index.unspent().collect::<Vec<_>>(), vec![(&foreign, &"x")]);
+
+            // A spend is only reported once.
+            prop_assert!(index.ingest_bundle(13, &bundle).is_empty());
+
+            // Rolling back block 12 unmarks the spends; rolling back block 10 also forgets
+            // the later notes.
+            index.rollback(11);
+            prop_assert_eq!(index.unspent().count(), 3);
+            index.rollback(10);
+            prop_assert_eq!(index.len(), 1);
+            prop_assert_eq!(index.get(&nullifiers[1]), Some((&"b", false)));
+        }
+
+        #[test]
+        fn compact_spends_are_detected(
+            sk in any::<[u8; 32]>().prop_filter_map("valid spending key", |bytes| {
+                Option::<SpendingKey>::from(SpendingKey::from_bytes(bytes))
+            }),
+            note in arb_note(NoteValue::from_raw(1000), NoteVersion::V2),
+            other in arb_note(NoteValue::from_raw(2000), NoteVersion::V2),
+        ) {
+            let fvk = FullViewingKey::from(&sk);
+            let mut index = NullifierIndex::new();
+            let nf = index.insert_note(&note, &fvk, 0u64, 100);
+            let other_nf = index.insert_note(&other, &fvk, 1u64, 101);
+            prop_assert_eq!(nf, note.nullifier(&fvk));
+
+            let action = |nullifier| {
+                CompactAction::from_parts(
+                    nullifier,
+                    ExtractedNoteCommitment::from(note.commitment()),
+                    EphemeralKeyBytes([0; 32]),
+                    [0; 52],
+                )
+            };
+            let spends = index.ingest_compact_actions(102, &[action(other_nf), action(nf)]);
+            prop_assert_eq!(
+                spends.iter().map(|spend| (*spend.note_ref(), spend.action_idx())).collect::<Vec<_>>(),
+                vec![(1, 0), (0, 1)]
+            );
+
+            // Once block 102 is final, the spent notes are no longer needed.
+            index.prune_spent(102);
+            prop_assert!(index.is_empty());
+        }
+    }
+}
@@ -22,6 +22,8 @@ use crate::{
     Address, Note,
 };
 
+pub mod scan;
+
 const PRF_OCK_ORCHARD_PERSONALIZATION: &[u8; 16] = b"Zcash_Orchardock";
 
 /// Defined in [Zcash Protocol Spec § 5.4.2: Pseudo Random Functions][concreteprfs].
@@ -212,6 +214,15 @@ impl BundleDomain {
             policy: BundleDomainPolicy { note_version },
         }
     }
+
+    /// Constructs a domain that can be used to decrypt the output note with the given
+    /// `rho` as a note of `note_version`.
+    pub(crate) fn for_rho(rho: Rho, note_version: NoteVersion) -> Self {
+        Self {
+            rho,
+            policy: BundleDomainPolicy { note_version },
+        }
+    }
 }
 
 impl<P: DomainPolicy> Domain for NoteEncryptionDomain<P> {
@@ -428,6 +439,22 @@ impl<P: DomainPolicy> ShieldedOutput<NoteEncryptionDomain<P>, ENC_CIPHERTEXT_SIZ
     }
 }
 
+impl<P: DomainPolicy> ShieldedOutput<NoteEncryptionDomain<P>, ENC_CIPHERTEXT_SIZE>
+    for crate::pczt::Output
+{
+    fn ephemeral_key(&self) -> EphemeralKeyBytes {
+        EphemeralKeyBytes(self.encrypted_note().epk_bytes)
+    }
+
+    fn cmstar_bytes(&self) -> [u8; 32] {
+        self.cmx().to_bytes()
+    }
+
+    fn enc_ciphertext(&self) -> &[u8; ENC_CIPHERTEXT_SIZE] {
+        &self.encrypted_note().enc_ciphertext
+    }
+}
+
 impl<P: DomainPolicy> ShieldedOutput<NoteEncryptionDomain<P>, COMPACT_NOTE_SIZE> for CompactAction {
     fn ephemeral_key(&self) -> EphemeralKeyBytes {
         EphemeralKeyBytes(self.ephemeral_key.0)
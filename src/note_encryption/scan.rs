@@ -0,0 +1,478 @@
+//! Batched trial decryption of many actions with many incoming viewing keys.
+//!
+//! A wallet scanner typically holds the incoming viewing keys of many accounts and
+//! scans every action of a range of blocks. The functions in this module trial-decrypt
+//! all of the given actions with all of the given keys as one batch, whatever their
+//! bundles and value pools: every ephemeral key is prepared once for the whole batch,
+//! and each viewing key is decomposed once and agreed against every ephemeral key.
+//!
+//! Each action is decrypted under the note plaintext version of its value pool, as
+//! [`OrchardDomain`] and [`IronwoodDomain`] would: an Orchard action only yields V2 note
+//! plaintexts, and an Ironwood action only yields V3 note plaintexts.
+//!
+//! [`OrchardDomain`]: super::OrchardDomain
+//! [`IronwoodDomain`]: super::IronwoodDomain
+
+use alloc::vec::Vec;
+
+use zcash_note_encryption::{batch, EphemeralKeyBytes, ShieldedOutput, COMPACT_NOTE_SIZE};
+#[cfg(feature = "multicore")]
+use {alloc::collections::VecDeque, rayon::prelude::*};
+
+use super::{BundleDomain, CompactAction};
+use crate::{
+    bundle::{Authorization, Bundle},
+    keys::PreparedIncomingViewingKey,
+    memo::MemoBytes,
+    note::NoteVersion,
+    Address, Note, ValuePool,
+};
+
+/// A borrowed output, so that batches can be assembled without cloning actions.
+struct Borrowed<'a, O>(&'a O);
+
+impl<O: ShieldedOutput<BundleDomain, N>, const N: usize> ShieldedOutput<BundleDomain, N>
+    for Borrowed<'_, O>
+{
+    fn ephemeral_key(&self) -> EphemeralKeyBytes {
+        self.0.ephemeral_key()
+    }
+
+    fn cmstar_bytes(&self) -> [u8; 32] {
+        self.0.cmstar_bytes()
+    }
+
+    fn enc_ciphertext(&self) -> &[u8; N] {
+        self.0.enc_ciphertext()
+    }
+}
+
+/// The note plaintext version of the notes in a bundle of the given value pool.
+fn pool_note_version(value_pool: ValuePool) -> NoteVersion {
+    match value_pool {
+        ValuePool::Orchard => NoteVersion::V2,
+        ValuePool::Ironwood => NoteVersion::V3,
+    }
+}
+
+/// Trial-decrypts every action of every bundle in `bundles` with every key in `ivks`.
+///
+/// Returns a `(bundle_idx, action_idx, key_idx, note, address, memo)` tuple for each
+/// action that decrypts, in bundle and action order, where `bundle_idx` is the position
+/// of the bundle in `bundles`, `action_idx` the position of the action in its bundle,
+/// and `key_idx` the position in `ivks` of the first key that decrypts it.
+///
+/// Each bundle's actions are decrypted under the note plaintext version of its
+/// [`BundleVersion`](crate::bundle::BundleVersion).
+pub fn scan_bundles<'a, T: Authorization + 'a, V: 'a>(
+    ivks: &[PreparedIncomingViewingKey],
+    bundles: impl IntoIterator<Item = &'a Bundle<T, V>>,
+) -> Vec<(usize, usize, usize, Note, Address, MemoBytes)> {
+    let (indices, outputs): (Vec<_>, Vec<_>) = bundles
+        .into_iter()
+        .enumerate()
+        .flat_map(|(bundle_idx, bundle)| {
+            let note_version = bundle.bundle_version().note_version();
+            bundle
+                .actions()
+                .iter()
+                .enumerate()
+                .map(move |(action_idx, action)| {
+                    (
+                        (bundle_idx, action_idx),
+                        (
+                            BundleDomain::for_action(action, note_version),
+                            Borrowed(action),
+                        ),
+                    )
+                })
+        })
+        .unzip();
+
+    batch::try_note_decryption(ivks, &outputs)
+        .into_iter()
+        .zip(indices)
+        .filter_map(|(hit, (bundle_idx, action_idx))| {
+            hit.map(|((note, address, memo), key_idx)| {
+                (bundle_idx, action_idx, key_idx, note, address, memo.into())
+            })
+        })
+        .collect()
+}
+
+/// Trial-decrypts every compact action of every bundle in `bundles` with every key in
+/// `ivks`.
+///
+/// Each bundle is given as the value pool it belongs to and its compact actions, and its
+/// actions are decrypted under that pool's note plaintext version.
+///
+/// Returns a `(bundle_idx, action_idx, key_idx, note, address)` tuple for each action
+/// that decrypts, in bundle and action order, where `bundle_idx` is the position of the
+/// bundle in `bundles`, `action_idx` the position of the action in its bundle, and
+/// `key_idx` the position in `ivks` of the first key that decrypts it. Compact actions
+/// do not carry the memo.
+pub fn scan_compact_bundles<'a>(
+    ivks: &[PreparedIncomingViewingKey],
+    bundles: impl IntoIterator<Item = (ValuePool, &'a [CompactAction])>,
+) -> Vec<(usize, usize, usize, Note, Address)> {
+    let (indices, outputs): (Vec<_>, Vec<_>) = bundles
+        .into_iter()
+        .enumerate()
+        .flat_map(|(bundle_idx, (value_pool, actions))| {
+            let note_version = pool_note_version(value_pool);
+            actions.iter().enumerate().map(move |(action_idx, action)| {
+                (
+                    (bundle_idx, action_idx),
+                    (
+                        BundleDomain::for_rho(action.rho(), note_version),
+                        Borrowed(action),
+                    ),
+                )
+            })
+        })
+        .unzip();
+
+    decrypt_compact(ivks, &indices, &outputs)
+}
+
+/// Trial-decrypts `outputs` with `ivks` as one batch, returning the hits labelled with the
+/// corresponding `indices`.
+fn decrypt_compact<O: ShieldedOutput<BundleDomain, COMPACT_NOTE_SIZE>>(
+    ivks: &[PreparedIncomingViewingKey],
+    indices: &[(usize, usize)],
+    outputs: &[(BundleDomain, O)],
+) -> Vec<(usize, usize, usize, Note, Address)> {
+    batch::try_compact_note_decryption(ivks, outputs)
+        .into_iter()
+        .zip(indices)
+        .filter_map(|(hit, &(bundle_idx, action_idx))| {
+            hit.map(|((note, address), key_idx)| (bundle_idx, action_idx, key_idx, note, address))
+        })
+        .collect()
+}
+
+/// The default number of compact actions that a [`ParallelScanner`] decrypts as one batch
+/// on one thread.
+#[cfg(feature = "multicore")]
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+
+/// A scanner that trial-decrypts compact actions on the [`rayon`] thread pool.
+///
+/// The actions are split into chunks of consecutive actions, and each chunk is decrypted
+/// as one batch (as by [`scan_compact_bundles`]) on a worker thread. The hits are returned
+/// in the same order as [`scan_compact_bundles`] returns them, however the chunks are
+/// scheduled. As compact actions do not carry the memo, neither do the hits.
+///
+/// The scanner uses the current rayon thread pool: the global pool by default, or the
+/// pool in which it is called via [`rayon::ThreadPool::install`].
+#[cfg(feature = "multicore")]
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelScanner<'a> {
+    ivks: &'a [PreparedIncomingViewingKey],
+    chunk_size: usize,
+    window: Option<usize>,
+}
+
+#[cfg(feature = "multicore")]
+impl<'a> ParallelScanner<'a> {
+    /// Constructs a scanner that trial-decrypts with every key in `ivks`, in chunks of
+    /// [`DEFAULT_CHUNK_SIZE`] actions.
+    pub fn new(ivks: &'a [PreparedIncomingViewingKey]) -> Self {
+        ParallelScanner {
+            ivks,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            window: None,
+        }
+    }
+
+    /// Sets the number of actions decrypted as one batch on one thread.
+    ///
+    /// Larger chunks amortize more of the per-batch work, while smaller chunks spread a
+    /// small scan across more threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be nonzero");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Sets the number of actions that a [`ScanStream`] buffers before decrypting them.
+    ///
+    /// By default, this is four chunks per thread in the current thread pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn with_window(mut self, window: usize) -> Self {
+        assert!(window > 0, "window must be nonzero");
+        self.window = Some(window);
+        self
+    }
+
+    fn window(&self) -> usize {
+        self.window.unwrap_or_else(|| {
+            self.chunk_size
+                .saturating_mul(4)
+                .saturating_mul(rayon::current_num_threads())
+        })
+    }
+
+    /// Trial-decrypts every compact action of every bundle in `bundles`.
+    ///
+    /// This returns exactly what [`scan_compact_bundles`] returns for the same keys and
+    /// bundles.
+    pub fn scan<'b>(
+        &self,
+        bundles: impl IntoIterator<Item = (ValuePool, &'b [CompactAction])>,
+    ) -> Vec<(usize, usize, usize, Note, Address)> {
+        let (indices, outputs): (Vec<_>, Vec<_>) = bundles
+            .into_iter()
+            .enumerate()
+            .flat_map(|(bundle_idx, (value_pool, actions))| {
+                let note_version = pool_note_version(value_pool);
+                actions.iter().enumerate().map(move |(action_idx, action)| {
+                    (
+                        (bundle_idx, action_idx),
+                        (
+                            BundleDomain::for_rho(action.rho(), note_version),
+                            Borrowed(action),
+                        ),
+                    )
+                })
+            })
+            .unzip();
+
+        self.decrypt(&indices, &outputs)
+    }
+
+    /// Returns an iterator over the hits of trial-decrypting every compact action of every
+    /// bundle in `bundles`, in the same order as [`Self::scan`] returns them.
+    ///
+    /// The bundles are consumed lazily, a window of actions at a time (see
+    /// [`Self::with_window`]), so memory use is bounded by the window size plus the size
+    /// of the largest bundle, however many bundles there are. This suits scanning a large
+    /// range of compact blocks as they are fetched.
+    pub fn scan_stream<I, B>(self, bundles: I) -> ScanStream<'a, I::IntoIter>
+    where
+        I: IntoIterator<Item = (ValuePool, B)>,
+        B: IntoIterator<Item = CompactAction>,
+    {
+        ScanStream {
+            scanner: self,
+            bundles: bundles.into_iter(),
+            next_bundle_idx: 0,
+            hits: VecDeque::new(),
+        }
+    }
+
+    /// Trial-decrypts `outputs` in parallel chunks.
+    fn decrypt<O: ShieldedOutput<BundleDomain, COMPACT_NOTE_SIZE> + Sync>(
+        &self,
+        indices: &[(usize, usize)],
+        outputs: &[(BundleDomain, O)],
+    ) -> Vec<(usize, usize, usize, Note, Address)> {
+        let chunks: Vec<Vec<_>> = outputs
+            .par_chunks(self.chunk_size)
+            .zip(indices.par_chunks(self.chunk_size))
+            .map(|(outputs, indices)| decrypt_compact(self.ivks, indices, outputs))
+            .collect();
+        chunks.into_iter().flatten().collect()
+    }
+}
+
+/// An iterator over the hits of a streaming parallel scan, returned by
+/// [`ParallelScanner::scan_stream`].
+#[cfg(feature = "multicore")]
+#[derive(Debug)]
+pub struct ScanStream<'a, I> {
+    scanner: ParallelScanner<'a>,
+    bundles: I,
+    next_bundle_idx: usize,
+    hits: VecDeque<(usize, usize, usize, Note, Address)>,
+}
+
+#[cfg(feature = "multicore")]
+impl<I, B> Iterator for ScanStream<'_, I>
+where
+    I: Iterator<Item = (ValuePool, B)>,
+    B: IntoIterator<Item = CompactAction>,
+{
+    type Item = (usize, usize, usize, Note, Address);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let window = self.scanner.window();
+        loop {
+            if let Some(hit) = self.hits.pop_front() {
+                return Some(hit);
+            }
+
+            // Buffer the next window of actions, always finishing the current bundle.
+            let mut indices = vec![];
+            let mut outputs = vec![];
+            while outputs.len() < window {
+                let Some((value_pool, actions)) = self.bundles.next() else {
+                    break;
+                };
+                let bundle_idx = self.next_bundle_idx;
+                self.next_bundle_idx += 1;
+                let note_version = pool_note_version(value_pool);
+                for (action_idx, action) in actions.into_iter().enumerate() {
+                    indices.push((bundle_idx, action_idx));
+                    outputs.push((BundleDomain::for_rho(action.rho(), note_version), action));
+                }
+            }
+            if outputs.is_empty() {
+                // The window is only left unfilled once the bundles are exhausted.
+                return None;
+            }
+
+            self.hits.extend(self.scanner.decrypt(&indices, &outputs));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use rand::rngs::OsRng;
+
+    use super::{scan_bundles, scan_compact_bundles};
+    use crate::{
+        builder::{Builder, BundleType},
+        bundle::{BundleVersion, Flags},
+        keys::{FullViewingKey, PreparedIncomingViewingKey, Scope, SpendingKey},
+        note_encryption::CompactAction,
+        tree::Anchor,
+        value::NoteValue,
+        Address, ValuePool,
+    };
+
+    #[test]
+    fn scan_matches_per_bundle_decryption() {
+        let mut rng = OsRng;
+
+        let fvks: Vec<FullViewingKey> = (0..3)
+            .map(|_| FullViewingKey::from(&SpendingKey::random(&mut rng)))
+            .collect();
+        let ivks: Vec<_> = fvks
+            .iter()
+            .flat_map(|fvk| [fvk.to_ivk(Scope::External), fvk.to_ivk(Scope::Internal)])
+            .collect();
+        let prepared: Vec<_> = ivks.iter().map(PreparedIncomingViewingKey::new).collect();
+        let foreign = FullViewingKey::from(&SpendingKey::random(&mut rng));
+
+        let bundle = |bundle_version: BundleVersion, recipients: &[Address]| {
+            let mut builder = Builder::new(
+                BundleType::DEFAULT,
+                bundle_version,
+                Flags::SPENDS_DISABLED,
+                Anchor::empty_tree(),
+            )
+            .unwrap();
+            for (i, recipient) in recipients.iter().enumerate() {
+                builder
+                    .add_output(
+                        None,
+                        *recipient,
+                        NoteValue::from_raw(1000 + i as u64),
+                        [i as u8; 512],
+                    )
+                    .unwrap();
+            }
+            builder.build::<i64>(OsRng).unwrap().unwrap().0
+        };
+        let bundles = [
+            bundle(
+                BundleVersion::orchard_v2(),
+                &[
+                    fvks[0].address_at(0u32, Scope::External),
+                    foreign.address_at(0u32, Scope::External),
+                    fvks[2].address_at(5u32, Scope::Internal),
+                ],
+            ),
+            bundle(
+                BundleVersion::ironwood_v3(),
+                &[foreign.address_at(1u32, Scope::External)],
+            ),
+            bundle(
+                BundleVersion::ironwood_v3(),
+                &[
+                    fvks[1].address_at(3u32, Scope::External),
+                    fvks[0].address_at(0u32, Scope::Internal),
+                ],
+            ),
+        ];
+
+        let mut expected = vec![];
+        for (bundle_idx, bundle) in bundles.iter().enumerate() {
+            for (action_idx, ivk, note, address, memo) in bundle.decrypt_outputs_with_keys(&ivks) {
+                let key_idx = ivks.iter().position(|k| *k == ivk).unwrap();
+                expected.push((bundle_idx, action_idx, key_idx, note, address, memo));
+            }
+        }
+        expected.sort_by_key(|hit| (hit.0, hit.1));
+        assert_eq!(expected.len(), 4);
+        assert_eq!(scan_bundles(&prepared, &bundles), expected);
+
+        let compact: Vec<(ValuePool, Vec<CompactAction>)> = bundles
+            .iter()
+            .map(|bundle| {
+                (
+                    bundle.bundle_version().value_pool(),
+                    bundle.actions().iter().map(CompactAction::from).collect(),
+                )
+            })
+            .collect();
+        let hits = scan_compact_bundles(
+            &prepared,
+            compact
+                .iter()
+                .map(|(pool, actions)| (*pool, actions.as_slice())),
+        );
+        assert_eq!(
+            hits,
+            expected
+                .iter()
+                .map(|(b, a, k, note, address, _)| (*b, *a, *k, *note, *address))
+                .collect::<Vec<_>>()
+        );
+
+        // Compact actions decrypted under the wrong pool's note version do not match.
+        let mislabelled = scan_compact_bundles(
+            &prepared,
+            compact.iter().map(|(pool, actions)| {
+                let pool = match pool {
+                    ValuePool::Orchard => ValuePool::Ironwood,
+                    ValuePool::Ironwood => ValuePool::Orchard,
+                };
+                (pool, actions.as_slice())
+            }),
+        );
+        assert!(mislabelled.is_empty());
+
+        // Scanning in parallel, with chunks that straddle bundle boundaries, and streaming
+        // with windows smaller than a bundle, both preserve the sequential order.
+        #[cfg(feature = "multicore")]
+        {
+            let scanner = super::ParallelScanner::new(&prepared).with_chunk_size(3);
+            assert_eq!(
+                scanner.scan(
+                    compact
+                        .iter()
+                        .map(|(pool, actions)| (*pool, actions.as_slice()))
+                ),
+                hits
+            );
+            for window in [1, 4, 100] {
+                let stream = scanner
+                    .with_window(window)
+                    .scan_stream(compact.iter().cloned());
+                assert_eq!(stream.collect::<Vec<_>>(), hits);
+            }
+        }
+    }
+}
@@ -17,6 +17,7 @@ use crate::{
     primitives::redpallas::{self, Binding, SpendAuth},
     tree::MerklePath,
     value::{NoteValue, ValueCommitTrapdoor, ValueCommitment, ValueSum},
+    zip32::DerivationPath,
     Address, Anchor, NoteVersion, Proof,
 };
 
@@ -24,7 +25,7 @@ mod parse;
 pub use parse::ParseError;
 
 mod verify;
-pub use verify::VerifyError;
+pub use verify::{SigningReport, SigningVerifyError, VerifyError};
 
 mod io_finalizer;
 pub use io_finalizer::IoFinalizerError;
@@ -32,6 +33,9 @@ pub use io_finalizer::IoFinalizerError;
 mod updater;
 pub use updater::{ActionUpdater, Updater, UpdaterError};
 
+mod redactor;
+pub use redactor::{ActionRedactor, Redactor, RedactorError};
+
 #[cfg(feature = "circuit")]
 mod prover;
 #[cfg(feature = "circuit")]
@@ -40,6 +44,12 @@ pub use prover::ProverError;
 mod signer;
 pub use signer::SignerError;
 
+mod combiner;
+pub use combiner::CombinerError;
+
+mod summary;
+pub use summary::{BundleSummary, OutputKind, OutputSummary, SummaryError};
+
 mod tx_extractor;
 pub use tx_extractor::{TxExtractorError, Unbound};
 
@@ -354,28 +364,35 @@ pub struct Zip32Derivation {
 }
 
 impl Zip32Derivation {
+    /// Constructs a derivation from a seed fingerprint and a derivation path.
+    pub fn from_path(seed_fingerprint: [u8; 32], path: DerivationPath) -> Self {
+        Self {
+            seed_fingerprint,
+            derivation_path: path.into(),
+        }
+    }
+
+    /// Returns the derivation path as a [`DerivationPath`].
+    pub fn path(&self) -> DerivationPath {
+        self.derivation_path.clone().into()
+    }
+
     /// Extracts the ZIP 32 account index from this derivation path.
     ///
     /// Returns `None` if the seed fingerprints don't match, or if this is a non-standard
-    /// derivation path.
+    /// derivation path. Use [`DerivationPath::account`] to also extract the account of a
+    /// path that continues below the account.
     pub fn extract_account_index(
         &self,
         seed_fp: &zip32::fingerprint::SeedFingerprint,
         expected_coin_type: zip32::ChildIndex,
     ) -> Option<zip32::AccountId> {
-        if self.seed_fingerprint == seed_fp.to_bytes() {
-            match &self.derivation_path[..] {
-                [purpose, coin_type, account_index]
-                    if purpose == &zip32::ChildIndex::hardened(32)
-                        && coin_type == &expected_coin_type =>
-                {
-                    Some(
-                        zip32::AccountId::try_from(account_index.index() - (1 << 31))
-                            .expect("zip32::ChildIndex only supports hardened"),
-                    )
-                }
-                _ => None,
-            }
+        let path = self.path();
+        if self.seed_fingerprint == seed_fp.to_bytes()
+            && path.is_account_path()
+            && path.as_slice()[1] == expected_coin_type
+        {
+            path.account()
         } else {
             None
         }
@@ -396,15 +413,17 @@ mod tests {
         circuit::{OrchardCircuitVersion, ProvingKey, VerifyingKey},
         constants::MERKLE_DEPTH_ORCHARD,
         keys::{FullViewingKey, Scope, SpendAuthorizingKey, SpendingKey},
+        memo::{Memo, TextMemo},
         note::{ExtractedNoteCommitment, NoteVersion, Nullifier, RandomSeed, Rho},
         pczt::{
-            IoFinalizerError, ParseError, ProverError, SignerError, TxExtractorError, VerifyError,
+            CombinerError, IoFinalizerError, OutputKind, ParseError, ProverError, RedactorError,
+            SignerError, SigningVerifyError, SummaryError, TxExtractorError, VerifyError,
             Zip32Derivation,
         },
         primitives::redpallas::{self, SpendAuth},
         tree::{MerkleHashOrchard, MerklePath, EMPTY_ROOTS},
         value::NoteValue,
-        Note,
+        Address, Note,
     };
 
     /// Builds a cross-address-restricted pczt bundle with one real spend (15_000 at an
@@ -508,6 +527,72 @@ mod tests {
         builder.build_for_pczt(&mut rng).unwrap().0
     }
 
+    /// Returns a field-by-field copy of `bundle`, as a Combiner would receive from each of
+    /// several parties that updated the same PCZT in parallel.
+    fn duplicate(bundle: &super::Bundle) -> super::Bundle {
+        let actions = bundle
+            .actions
+            .iter()
+            .map(|action| super::Action {
+                cv_net: action.cv_net.clone(),
+                spend: super::Spend {
+                    nullifier: action.spend.nullifier,
+                    rk: action.spend.rk.clone(),
+                    spend_auth_sig: action.spend.spend_auth_sig.clone(),
+                    recipient: action.spend.recipient,
+                    value: action.spend.value,
+                    rho: action.spend.rho,
+                    rseed: action.spend.rseed,
+                    fvk: action.spend.fvk.clone(),
+                    note_version: action.spend.note_version,
+                    witness: action.spend.witness.clone(),
+                    alpha: action.spend.alpha,
+                    zip32_derivation: action.spend.zip32_derivation.as_ref().map(|d| {
+                        Zip32Derivation {
+                            seed_fingerprint: d.seed_fingerprint,
+                            derivation_path: d.derivation_path.clone(),
+                        }
+                    }),
+                    dummy_sk: action.spend.dummy_sk,
+                    proprietary: action.spend.proprietary.clone(),
+                },
+                output: super::Output {
+                    cmx: action.output.cmx,
+                    note_version: action.output.note_version,
+                    encrypted_note: action.output.encrypted_note.clone(),
+                    recipient: action.output.recipient,
+                    value: action.output.value,
+                    rseed: action.output.rseed,
+                    ock: action
+                        .output
+                        .ock
+                        .as_ref()
+                        .map(|ock| zcash_note_encryption::OutgoingCipherKey(ock.0)),
+                    zip32_derivation: action.output.zip32_derivation.as_ref().map(|d| {
+                        Zip32Derivation {
+                            seed_fingerprint: d.seed_fingerprint,
+                            derivation_path: d.derivation_path.clone(),
+                        }
+                    }),
+                    user_address: action.output.user_address.clone(),
+                    proprietary: action.output.proprietary.clone(),
+                },
+                rcv: action.rcv.clone(),
+            })
+            .collect();
+
+        super::Bundle {
+            actions,
+            flags: bundle.flags,
+            bundle_version: bundle.bundle_version,
+            value_sum: bundle.value_sum,
+            anchor: bundle.anchor,
+            anchor_deferred: bundle.anchor_deferred,
+            zkproof: bundle.zkproof.clone(),
+            bsk: bundle.bsk.clone(),
+        }
+    }
+
     fn identity_rk() -> redpallas::VerificationKey<SpendAuth> {
         redpallas::VerificationKey::<SpendAuth>::try_from([0u8; 32])
             .expect("plain redpallas accepts the identity encoding")
@@ -1527,4 +1612,467 @@ mod tests {
         let bundle = pczt_bundle.extract::<i64>().unwrap().unwrap();
         assert!(!bundle.flags().cross_address_enabled());
     }
+
+    #[test]
+    fn combiner_merges_parallel_signatures() {
+        let rng = OsRng;
+        let (mut pczt_bundle, bundle_meta, spend_ask, change_ask) = restricted_pczt_bundle(rng);
+        let sighash = [0; 32];
+        pczt_bundle.finalize_io(sighash, rng).unwrap();
+
+        let spend_action_index = bundle_meta.spend_action_index(0).unwrap();
+        let change_action_index = bundle_meta.output_action_index(0).unwrap();
+
+        // Two Signers each sign their own spend in a separate copy of the PCZT, and one of
+        // them also attaches proprietary data.
+        let mut first = duplicate(&pczt_bundle);
+        let mut second = pczt_bundle;
+        first.actions_mut()[spend_action_index]
+            .sign(sighash, &spend_ask, rng)
+            .unwrap();
+        first
+            .update_with(|mut u| {
+                u.update_action_with(spend_action_index, |mut a| {
+                    a.set_spend_proprietary("signer".into(), vec![1]);
+                    Ok(())
+                })
+            })
+            .unwrap();
+        second.actions_mut()[change_action_index]
+            .sign(sighash, &change_ask, rng)
+            .unwrap();
+
+        let merged = first.merge(second).unwrap();
+        for action in merged.actions() {
+            assert!(action.spend().spend_auth_sig().is_some());
+        }
+        assert_eq!(
+            merged.actions()[spend_action_index]
+                .spend()
+                .proprietary()
+                .get("signer"),
+            Some(&vec![1])
+        );
+
+        // Merging is idempotent.
+        let copy = duplicate(&merged);
+        assert!(merged.merge(copy).is_ok());
+    }
+
+    #[test]
+    fn combiner_rejects_conflicts_and_mismatched_effects() {
+        let rng = OsRng;
+        let (pczt_bundle, _, _, _) = restricted_pczt_bundle(rng);
+
+        let set_user_address = |bundle: &mut super::Bundle, user_address: &str| {
+            bundle
+                .update_with(|mut u| {
+                    u.update_action_with(1, |mut a| {
+                        a.set_output_user_address(user_address.into());
+                        Ok(())
+                    })
+                })
+                .unwrap();
+        };
+        let mut first = duplicate(&pczt_bundle);
+        let mut second = duplicate(&pczt_bundle);
+        set_user_address(&mut first, "u1first");
+        set_user_address(&mut second, "u1second");
+        assert_eq!(
+            first.merge(second).err(),
+            Some(CombinerError::ActionFieldConflict {
+                index: 1,
+                field: "output.user_address",
+            })
+        );
+
+        // A different bundle has different effecting data.
+        let (other_bundle, _, _, _) = restricted_pczt_bundle(rng);
+        assert_eq!(
+            pczt_bundle.merge(other_bundle).err(),
+            Some(CombinerError::EffectsMismatch)
+        );
+    }
+
+    #[test]
+    fn redactor_refuses_fields_needed_by_later_roles() {
+        let rng = OsRng;
+        let pk = ProvingKey::build(OrchardCircuitVersion::PostNu6_3);
+        let (mut pczt_bundle, bundle_meta, spend_ask, change_ask) = restricted_pczt_bundle(rng);
+        let sighash = [0; 32];
+        let spend_action_index = bundle_meta.spend_action_index(0).unwrap();
+        let change_action_index = bundle_meta.output_action_index(0).unwrap();
+
+        assert_eq!(
+            pczt_bundle.redact_with(|r| r.clear_spend_values()),
+            Err(RedactorError::FieldRequired {
+                index: 0,
+                field: "spend.value",
+                role: "Prover",
+            })
+        );
+        assert_eq!(
+            pczt_bundle.redact_with(|r| r.redact_action_with(2, |a| a.clear_user_address())),
+            Err(RedactorError::InvalidIndex)
+        );
+
+        // Metadata that no role needs can be redacted at any time.
+        pczt_bundle
+            .update_with(|mut u| {
+                u.update_action_with(change_action_index, |mut a| {
+                    a.set_output_user_address("u1change".into());
+                    Ok(())
+                })
+            })
+            .unwrap();
+        pczt_bundle
+            .redact_with(|r| r.redact_action_with(change_action_index, |a| a.clear_user_address()))
+            .unwrap();
+        assert!(pczt_bundle.actions()[change_action_index]
+            .output()
+            .user_address()
+            .is_none());
+
+        pczt_bundle.create_proof(&pk, rng).unwrap();
+
+        // The restricted bundle's IO Finalizer still needs `rcv` and the recipients.
+        assert_eq!(
+            pczt_bundle.redact_with(|r| r.clear_output_recipients()),
+            Err(RedactorError::FieldRequired {
+                index: 0,
+                field: "output.recipient",
+                role: "IO Finalizer",
+            })
+        );
+        assert_eq!(
+            pczt_bundle.finalize_spends(),
+            Err(RedactorError::FieldRequired {
+                index: 0,
+                field: "rcv",
+                role: "IO Finalizer",
+            })
+        );
+
+        // The proof no longer needs the note openings.
+        pczt_bundle.redact_with(|r| r.clear_spend_values()).unwrap();
+        pczt_bundle.finalize_io(sighash, rng).unwrap();
+
+        pczt_bundle.actions_mut()[spend_action_index]
+            .sign(sighash, &spend_ask, rng)
+            .unwrap();
+        assert_eq!(
+            pczt_bundle.finalize_spends(),
+            Err(RedactorError::FieldRequired {
+                index: change_action_index,
+                field: "spend.alpha",
+                role: "Signer",
+            })
+        );
+        pczt_bundle.actions_mut()[change_action_index]
+            .sign(sighash, &change_ask, rng)
+            .unwrap();
+
+        pczt_bundle.finalize_spends().unwrap();
+        for action in pczt_bundle.actions() {
+            assert!(action.rcv().is_none());
+            assert!(action.spend().alpha().is_none());
+            assert!(action.spend().rseed().is_none());
+            assert!(action.output().value().is_none());
+            assert!(action.output().rseed().is_none());
+        }
+
+        // The Transaction Extractor has everything it needs.
+        let bundle = pczt_bundle
+            .extract::<i64>()
+            .unwrap()
+            .unwrap()
+            .apply_binding_signature(sighash, rng)
+            .unwrap();
+        assert_eq!(bundle.actions().len(), 2);
+    }
+
+    #[test]
+    fn output_ock_verifies_out_ciphertext() {
+        let mut rng = OsRng;
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let bundle_version = BundleVersion::orchard_v2();
+        let mut builder = Builder::new(
+            BundleType::DEFAULT,
+            bundle_version,
+            bundle_version.default_flags(),
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        )
+        .unwrap();
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::External)),
+                recipient,
+                NoteValue::from_raw(5000),
+                [0u8; 512],
+            )
+            .unwrap();
+        let (mut pczt_bundle, bundle_meta) = builder.build_for_pczt(&mut rng).unwrap();
+        let output_action_index = bundle_meta.output_action_index(0).unwrap();
+
+        // Outputs added with an OVK carry their `ock`; padding outputs have none.
+        for (index, action) in pczt_bundle.actions().iter().enumerate() {
+            let result = action.output().verify_out_ciphertext(action.spend());
+            if index == output_action_index {
+                result.unwrap();
+            } else {
+                assert!(matches!(result, Err(VerifyError::MissingOutgoingCipherKey)));
+            }
+        }
+
+        // A different `ock` does not decrypt the `out_ciphertext`.
+        let action = &mut pczt_bundle.actions_mut()[output_action_index];
+        action.output.ock = Some(zcash_note_encryption::OutgoingCipherKey([7; 32]));
+        assert!(matches!(
+            action.output().verify_out_ciphertext(action.spend()),
+            Err(VerifyError::InvalidOutCiphertext)
+        ));
+    }
+
+    /// Builds a pczt bundle that spends a 15_000 note of the returned FVK, pays 7_000 (with
+    /// the memo `[1; 512]`) to the returned external address and 5_000 to the FVK's
+    /// internal address, leaving 3_000 as the value balance.
+    fn payment_pczt_bundle(
+        mut rng: OsRng,
+    ) -> (super::Bundle, BundleMetadata, FullViewingKey, Address) {
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let external =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let bundle_version = BundleVersion::orchard_v2();
+
+        let note = Note::new(
+            fvk.address_at(0u32, Scope::External),
+            NoteValue::from_raw(15_000),
+            Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+            bundle_version.note_version(),
+            &mut rng,
+        );
+        let merkle_path = MerklePath::dummy(&mut rng);
+        let anchor = merkle_path.root(note.commitment().into());
+        let mut builder = Builder::new(
+            BundleType::DEFAULT,
+            bundle_version,
+            bundle_version.default_flags(),
+            anchor,
+        )
+        .unwrap();
+        builder.add_spend(fvk.clone(), note, merkle_path).unwrap();
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::External)),
+                external,
+                NoteValue::from_raw(7_000),
+                [1u8; 512],
+            )
+            .unwrap();
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::Internal)),
+                fvk.address_at(0u32, Scope::Internal),
+                NoteValue::from_raw(5_000),
+                [0u8; 512],
+            )
+            .unwrap();
+        let (pczt_bundle, bundle_meta) = builder.build_for_pczt(&mut rng).unwrap();
+        (pczt_bundle, bundle_meta, fvk, external)
+    }
+
+    #[test]
+    fn verify_for_signing_reports_value_flows() {
+        let (mut pczt_bundle, bundle_meta, fvk, external) = payment_pczt_bundle(OsRng);
+
+        let expected = [(external, NoteValue::from_raw(7_000))];
+        let report = pczt_bundle.verify_for_signing(&fvk, &expected).unwrap();
+        assert_eq!(*report.spent(), 15_000);
+        assert_eq!(*report.other_spent(), 0);
+        assert_eq!(report.recipients(), &[(external, 7_000)]);
+        assert_eq!(*report.change(), 5_000);
+        assert_eq!(i64::try_from(*report.value_balance()).unwrap(), 3_000);
+
+        // Payments must match the expected outputs exactly.
+        assert!(matches!(
+            pczt_bundle.verify_for_signing(&fvk, &[]),
+            Err(SigningVerifyError::UnexpectedOutput { index })
+                if index == bundle_meta.output_action_index(0).unwrap()
+        ));
+        let missing = (external, NoteValue::from_raw(1));
+        assert!(matches!(
+            pczt_bundle.verify_for_signing(&fvk, &[expected[0], missing]),
+            Err(SigningVerifyError::MissingExpectedOutput { .. })
+        ));
+
+        // A tampered note ciphertext is detected.
+        let index = bundle_meta.output_action_index(1).unwrap();
+        pczt_bundle.actions_mut()[index]
+            .output
+            .encrypted_note
+            .enc_ciphertext[60] ^= 1;
+        assert!(matches!(
+            pczt_bundle.verify_for_signing(&fvk, &expected),
+            Err(SigningVerifyError::Action {
+                index: i,
+                error: VerifyError::InvalidEncCiphertext,
+            }) if i == index
+        ));
+    }
+
+    #[test]
+    fn verify_for_signing_tolerates_randomized_outputs() {
+        let rng = OsRng;
+        let (pczt_bundle, bundle_meta, _, _) = restricted_pczt_bundle(rng);
+        let spend_action = &pczt_bundle.actions()[bundle_meta.spend_action_index(0).unwrap()];
+        let change_action = &pczt_bundle.actions()[bundle_meta.output_action_index(0).unwrap()];
+        let fvk = spend_action.spend().fvk().clone().unwrap();
+
+        // The zero-valued output paired with the real spend has a randomized ciphertext.
+        assert!(matches!(
+            spend_action
+                .output()
+                .verify_enc_ciphertext(spend_action.spend()),
+            Err(VerifyError::InvalidEncCiphertext)
+        ));
+
+        // The change output belongs to a different wallet's key, so to this Signer it is
+        // a payment.
+        let change = (
+            change_action.output().recipient().unwrap(),
+            NoteValue::from_raw(5_000),
+        );
+        let report = pczt_bundle.verify_for_signing(&fvk, &[change]).unwrap();
+        assert_eq!(*report.spent(), 15_000);
+        assert_eq!(report.recipients(), &[(change.0, 5_000)]);
+        assert_eq!(*report.change(), 0);
+        assert_eq!(i64::try_from(*report.value_balance()).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn verify_for_signing_rejects_tampered_zero_valued_outputs() {
+        let mut rng = OsRng;
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let bundle_version = BundleVersion::orchard_v2();
+        let mut builder = Builder::new(
+            BundleType::DEFAULT,
+            bundle_version,
+            bundle_version.default_flags(),
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        )
+        .unwrap();
+        builder
+            .add_output(None, recipient, NoteValue::from_raw(5_000), [0u8; 512])
+            .unwrap();
+        let (mut pczt_bundle, bundle_meta) = builder.build_for_pczt(&mut rng).unwrap();
+        let expected = [(recipient, NoteValue::from_raw(5_000))];
+        pczt_bundle.verify_for_signing(&fvk, &expected).unwrap();
+
+        // Cross-address transfers are enabled, so the zero-valued padding output must
+        // still have a valid note ciphertext.
+        let index = 1 - bundle_meta.output_action_index(0).unwrap();
+        let output = &mut pczt_bundle.actions_mut()[index].output;
+        assert_eq!(output.value, Some(NoteValue::ZERO));
+        output.encrypted_note.enc_ciphertext[60] ^= 1;
+        assert!(matches!(
+            pczt_bundle.verify_for_signing(&fvk, &expected),
+            Err(SigningVerifyError::Action {
+                index: i,
+                error: VerifyError::InvalidEncCiphertext,
+            }) if i == index
+        ));
+    }
+
+    #[test]
+    fn summarize_classifies_outputs() {
+        let (mut pczt_bundle, bundle_meta, fvk, external) = payment_pczt_bundle(OsRng);
+        let payment_index = bundle_meta.output_action_index(0).unwrap();
+        let change_index = bundle_meta.output_action_index(1).unwrap();
+        pczt_bundle
+            .update_with(|mut u| {
+                u.update_action_with(payment_index, |mut a| {
+                    a.set_output_user_address("u1payment".into());
+                    Ok(())
+                })
+            })
+            .unwrap();
+
+        let summary = pczt_bundle.summarize(&fvk).unwrap();
+        assert_eq!(summary.outputs().len(), pczt_bundle.actions().len());
+        for output in summary.outputs() {
+            match *output.index() {
+                i if i == payment_index => {
+                    assert_eq!(*output.kind(), OutputKind::ExternalPayment);
+                    assert_eq!(*output.recipient(), external);
+                    assert_eq!(output.user_address().as_deref(), Some("u1payment"));
+                    assert_eq!(output.memo().map(<[u8; 512]>::from), Some([1; 512]));
+                }
+                i if i == change_index => {
+                    assert_eq!(*output.kind(), OutputKind::Change(Scope::Internal));
+                    assert_eq!(output.memo().map(<[u8; 512]>::from), Some([0; 512]));
+                    // An all-zero memo field is empty text.
+                    assert_eq!(
+                        output.memo().map(|m| m.parse()),
+                        Some(Ok(Memo::Text(TextMemo::new("").unwrap())))
+                    );
+                }
+                _ => {
+                    assert_eq!(*output.kind(), OutputKind::Dummy);
+                    assert!(output.memo().is_none());
+                }
+            }
+        }
+        assert_eq!(*summary.spent(), 15_000);
+        assert_eq!(summary.total_sent(), Some(7_000));
+        assert_eq!(summary.total_change(), Some(5_000));
+        assert_eq!(i64::try_from(*summary.value_balance()).unwrap(), 3_000);
+
+        // The value sum must match the classified values.
+        pczt_bundle.actions_mut()[payment_index].output.value = Some(NoteValue::from_raw(6_000));
+        assert!(matches!(
+            pczt_bundle.summarize(&fvk),
+            Err(SummaryError::ValueSumMismatch)
+        ));
+    }
+
+    #[test]
+    fn summarize_identifies_fabricated_outputs() {
+        let rng = OsRng;
+        let (pczt_bundle, bundle_meta, _, _) = restricted_pczt_bundle(rng);
+        let spend_index = bundle_meta.spend_action_index(0).unwrap();
+        let change_index = bundle_meta.output_action_index(0).unwrap();
+        let change_fvk = pczt_bundle.actions()[change_index]
+            .spend()
+            .fvk()
+            .clone()
+            .unwrap();
+
+        let summary = pczt_bundle.summarize(&change_fvk).unwrap();
+        assert_eq!(
+            *summary.outputs()[spend_index].kind(),
+            OutputKind::Fabricated
+        );
+        assert_eq!(
+            *summary.outputs()[change_index].kind(),
+            OutputKind::Change(Scope::Internal)
+        );
+        assert_eq!(summary.total_change(), Some(5_000));
+        assert_eq!(i64::try_from(*summary.value_balance()).unwrap(), 10_000);
+
+        // A zero-valued output to a third party is not hidden as fabricated.
+        let mut pczt_bundle = pczt_bundle;
+        let third_party = FullViewingKey::from(&SpendingKey::random(&mut OsRng))
+            .address_at(0u32, Scope::External);
+        pczt_bundle.actions_mut()[spend_index].output.recipient = Some(third_party);
+        let summary = pczt_bundle.summarize(&change_fvk).unwrap();
+        let output = &summary.outputs()[spend_index];
+        assert_eq!(*output.kind(), OutputKind::ExternalPayment);
+        assert_eq!(*output.recipient(), third_party);
+        assert!(output.memo().is_none());
+    }
 }
@@ -0,0 +1,239 @@
+use core::fmt;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Action, Bundle, Output, Spend};
+
+impl Bundle {
+    /// Merges this bundle with another bundle for the same transaction (the Combiner role).
+    ///
+    /// This is used to combine PCZTs that were updated or signed in parallel: for example,
+    /// when several Signers each return a copy of the bundle holding only their own
+    /// `spend_auth_sig`s.
+    ///
+    /// The two bundles must have identical effecting data: the same flags, bundle version,
+    /// `value_sum` and anchor, and the same number of actions with identical `cv_net`,
+    /// nullifiers, `rk`s, `cmx`s, transmitted note ciphertexts, and note versions. A bundle
+    /// whose anchor is still deferred merges with one whose real anchor has been installed
+    /// by an Updater, taking the real anchor.
+    ///
+    /// Every optional field is then the union of the two bundles' values. A field set in
+    /// both bundles must have the same value in each, and a proprietary key present in both
+    /// must map to the same value.
+    pub fn merge(mut self, other: Self) -> Result<Self, CombinerError> {
+        if self.flags != other.flags
+            || self.bundle_version != other.bundle_version
+            || self.value_sum != other.value_sum
+            || self.actions.len() != other.actions.len()
+        {
+            return Err(CombinerError::EffectsMismatch);
+        }
+
+        match (self.anchor_deferred, other.anchor_deferred) {
+            (true, false) => {
+                self.anchor = other.anchor;
+                self.anchor_deferred = false;
+            }
+            (false, true) => (),
+            _ => {
+                if self.anchor != other.anchor {
+                    return Err(CombinerError::EffectsMismatch);
+                }
+            }
+        }
+
+        // Check the effecting data of every action before modifying any of them, so that a
+        // mismatch is reported as such even if an earlier action has a field conflict.
+        for (a, b) in self.actions.iter().zip(other.actions.iter()) {
+            if !a.has_same_effects(b) {
+                return Err(CombinerError::EffectsMismatch);
+            }
+        }
+
+        for (index, (a, b)) in self.actions.iter_mut().zip(other.actions).enumerate() {
+            a.merge(b)
+                .map_err(|field| CombinerError::ActionFieldConflict { index, field })?;
+        }
+
+        merge_field(&mut self.zkproof, other.zkproof, |a, b| {
+            a.as_ref() == b.as_ref()
+        })
+        .map_err(|()| CombinerError::BundleFieldConflict("zkproof"))?;
+        merge_field(&mut self.bsk, other.bsk, |a, b| {
+            <[u8; 32]>::from(a) == <[u8; 32]>::from(b)
+        })
+        .map_err(|()| CombinerError::BundleFieldConflict("bsk"))?;
+
+        Ok(self)
+    }
+}
+
+impl Action {
+    /// Returns whether this action and `other` have the same effecting data.
+    fn has_same_effects(&self, other: &Self) -> bool {
+        let (a, b) = (&self.output.encrypted_note, &other.output.encrypted_note);
+        self.cv_net.to_bytes() == other.cv_net.to_bytes()
+            && self.spend.nullifier == other.spend.nullifier
+            && self.spend.rk == other.spend.rk
+            && self.spend.note_version == other.spend.note_version
+            && self.output.cmx == other.output.cmx
+            && self.output.note_version == other.output.note_version
+            && a.epk_bytes == b.epk_bytes
+            && a.enc_ciphertext == b.enc_ciphertext
+            && a.out_ciphertext == b.out_ciphertext
+    }
+
+    /// Merges the optional fields of `other` into this action, which must have the same
+    /// effecting data. Returns the name of the first conflicting field on failure.
+    fn merge(&mut self, other: Self) -> Result<(), &'static str> {
+        merge_field(&mut self.rcv, other.rcv, |a, b| {
+            a.to_bytes() == b.to_bytes()
+        })
+        .map_err(|()| "rcv")?;
+        self.spend.merge(other.spend)?;
+        self.output.merge(other.output)
+    }
+}
+
+impl Spend {
+    fn merge(&mut self, other: Self) -> Result<(), &'static str> {
+        merge_field(&mut self.spend_auth_sig, other.spend_auth_sig, |a, b| {
+            <[u8; 64]>::from(a) == <[u8; 64]>::from(b)
+        })
+        .map_err(|()| "spend.spend_auth_sig")?;
+        merge_field(&mut self.recipient, other.recipient, PartialEq::eq)
+            .map_err(|()| "spend.recipient")?;
+        merge_field(&mut self.value, other.value, PartialEq::eq).map_err(|()| "spend.value")?;
+        merge_field(&mut self.rho, other.rho, PartialEq::eq).map_err(|()| "spend.rho")?;
+        merge_field(&mut self.rseed, other.rseed, |a, b| {
+            a.as_bytes() == b.as_bytes()
+        })
+        .map_err(|()| "spend.rseed")?;
+        merge_field(&mut self.fvk, other.fvk, PartialEq::eq).map_err(|()| "spend.fvk")?;
+        merge_field(&mut self.witness, other.witness, |a, b| {
+            a.position() == b.position() && a.auth_path() == b.auth_path()
+        })
+        .map_err(|()| "spend.witness")?;
+        merge_field(&mut self.alpha, other.alpha, PartialEq::eq).map_err(|()| "spend.alpha")?;
+        merge_field(
+            &mut self.zip32_derivation,
+            other.zip32_derivation,
+            PartialEq::eq,
+        )
+        .map_err(|()| "spend.zip32_derivation")?;
+        merge_field(&mut self.dummy_sk, other.dummy_sk, |a, b| {
+            a.to_bytes() == b.to_bytes()
+        })
+        .map_err(|()| "spend.dummy_sk")?;
+        merge_proprietary(&mut self.proprietary, other.proprietary)
+            .map_err(|()| "spend.proprietary")
+    }
+}
+
+impl Output {
+    fn merge(&mut self, other: Self) -> Result<(), &'static str> {
+        merge_field(&mut self.recipient, other.recipient, PartialEq::eq)
+            .map_err(|()| "output.recipient")?;
+        merge_field(&mut self.value, other.value, PartialEq::eq).map_err(|()| "output.value")?;
+        merge_field(&mut self.rseed, other.rseed, |a, b| {
+            a.as_bytes() == b.as_bytes()
+        })
+        .map_err(|()| "output.rseed")?;
+        merge_field(&mut self.ock, other.ock, |a, b| a.0 == b.0).map_err(|()| "output.ock")?;
+        merge_field(
+            &mut self.zip32_derivation,
+            other.zip32_derivation,
+            PartialEq::eq,
+        )
+        .map_err(|()| "output.zip32_derivation")?;
+        merge_field(&mut self.user_address, other.user_address, PartialEq::eq)
+            .map_err(|()| "output.user_address")?;
+        merge_proprietary(&mut self.proprietary, other.proprietary)
+            .map_err(|()| "output.proprietary")
+    }
+}
+
+/// Sets `lhs` to `rhs` if only `rhs` is set, and checks that the values are equal if both
+/// are set.
+fn merge_field<T>(
+    lhs: &mut Option<T>,
+    rhs: Option<T>,
+    eq: impl FnOnce(&T, &T) -> bool,
+) -> Result<(), ()> {
+    match (lhs.as_ref(), rhs) {
+        (_, None) => Ok(()),
+        (None, Some(rhs)) => {
+            *lhs = Some(rhs);
+            Ok(())
+        }
+        (Some(l), Some(r)) => {
+            if eq(l, &r) {
+                Ok(())
+            } else {
+                Err(())
+            }
+        }
+    }
+}
+
+/// Adds the entries of `rhs` to `lhs`, checking that keys present in both have the same
+/// value.
+fn merge_proprietary(
+    lhs: &mut BTreeMap<String, Vec<u8>>,
+    rhs: BTreeMap<String, Vec<u8>>,
+) -> Result<(), ()> {
+    for (key, value) in rhs {
+        match lhs.get(&key) {
+            Some(existing) if existing != &value => return Err(()),
+            Some(_) => (),
+            None => {
+                lhs.insert(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Errors that can occur while combining Orchard PCZT bundles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CombinerError {
+    /// The bundles have different effecting data, so they are not copies of the same
+    /// bundle.
+    EffectsMismatch,
+    /// A bundle-level field is set to different values in the two bundles.
+    BundleFieldConflict(&'static str),
+    /// A field of the action at `index` is set to different values in the two bundles.
+    ActionFieldConflict {
+        /// The index of the action within the bundle.
+        index: usize,
+        /// The name of the conflicting field, qualified by `spend.` or `output.` where it
+        /// belongs to the action's spend or output.
+        field: &'static str,
+    },
+}
+
+impl fmt::Display for CombinerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CombinerError::EffectsMismatch => {
+                write!(
+                    f,
+                    "The bundles being combined have different effecting data"
+                )
+            }
+            CombinerError::BundleFieldConflict(field) => {
+                write!(f, "The bundles being combined have conflicting `{field}`")
+            }
+            CombinerError::ActionFieldConflict { index, field } => write!(
+                f,
+                "The bundles being combined have conflicting `{field}` for action {index}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CombinerError {}
@@ -0,0 +1,459 @@
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use super::{Action, Bundle};
+
+impl Bundle {
+    /// Redacts private fields from the bundle as selected in the given closure (the
+    /// Redactor role).
+    ///
+    /// A field is only redacted once no later role still needs it:
+    /// - the Prover needs the note openings (`recipient`, `value`, `rho`, `rseed`, `fvk`,
+    ///   `witness`), `alpha`, and `rcv` until `zkproof` is set;
+    /// - the IO Finalizer needs `rcv` and `dummy_sk`, and the spend and output `recipient`s
+    ///   of a bundle that disables cross-address transfers, until `bsk` is set;
+    /// - the Signer (or the IO Finalizer, for a dummy spend) needs an action's `alpha` until
+    ///   its `spend_auth_sig` is set.
+    ///
+    /// The fields the Transaction Extractor needs (`spend_auth_sig`, `zkproof`, `bsk`, and
+    /// the effecting data) cannot be redacted.
+    ///
+    /// Every selection is checked before anything is redacted, so on error the bundle is
+    /// left unmodified.
+    pub fn redact_with<F>(&mut self, f: F) -> Result<(), RedactorError>
+    where
+        F: FnOnce(Redactor) -> Redactor,
+    {
+        let redactions = f(Redactor(Vec::new())).0;
+
+        for &(index, field) in &redactions {
+            let indices = match index {
+                Some(index) if index >= self.actions.len() => {
+                    return Err(RedactorError::InvalidIndex)
+                }
+                Some(index) => index..index + 1,
+                None => 0..self.actions.len(),
+            };
+            for index in indices {
+                if let Some(role) = field.required_by(self, &self.actions[index]) {
+                    return Err(RedactorError::FieldRequired {
+                        index,
+                        field: field.name(),
+                        role,
+                    });
+                }
+            }
+        }
+
+        for (index, field) in redactions {
+            match index {
+                Some(index) => field.clear(&mut self.actions[index]),
+                None => self
+                    .actions
+                    .iter_mut()
+                    .for_each(|action| field.clear(action)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Redacts every field that opens the bundle's notes or value commitments, once the
+    /// bundle is ready for the Transaction Extractor (the Spend Finalizer role).
+    ///
+    /// This clears `rcv`, and the `recipient`, `value`, `rseed`, `rho`, `fvk`, `witness`,
+    /// `alpha`, `dummy_sk` and `ock` fields of every action. It requires `zkproof`, `bsk`
+    /// and every `spend_auth_sig` to be set; otherwise the bundle is left unmodified and
+    /// the role that has yet to run is reported.
+    pub fn finalize_spends(&mut self) -> Result<(), RedactorError> {
+        self.redact_with(|r| {
+            r.clear_rcv()
+                .clear_spend_recipients()
+                .clear_spend_values()
+                .clear_rho()
+                .clear_spend_rseeds()
+                .clear_fvks()
+                .clear_witnesses()
+                .clear_alpha()
+                .clear_dummy_sks()
+                .clear_output_recipients()
+                .clear_output_values()
+                .clear_output_rseeds()
+                .clear_ocks()
+        })
+    }
+}
+
+/// A private field of a PCZT action that can be redacted.
+#[derive(Clone, Copy, Debug)]
+enum Field {
+    Rcv,
+    SpendRecipient,
+    SpendValue,
+    Rho,
+    SpendRseed,
+    Fvk,
+    Witness,
+    Alpha,
+    SpendZip32Derivation,
+    DummySk,
+    SpendProprietary,
+    OutputRecipient,
+    OutputValue,
+    OutputRseed,
+    Ock,
+    OutputZip32Derivation,
+    UserAddress,
+    OutputProprietary,
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::Rcv => "rcv",
+            Field::SpendRecipient => "spend.recipient",
+            Field::SpendValue => "spend.value",
+            Field::Rho => "spend.rho",
+            Field::SpendRseed => "spend.rseed",
+            Field::Fvk => "spend.fvk",
+            Field::Witness => "spend.witness",
+            Field::Alpha => "spend.alpha",
+            Field::SpendZip32Derivation => "spend.zip32_derivation",
+            Field::DummySk => "spend.dummy_sk",
+            Field::SpendProprietary => "spend.proprietary",
+            Field::OutputRecipient => "output.recipient",
+            Field::OutputValue => "output.value",
+            Field::OutputRseed => "output.rseed",
+            Field::Ock => "output.ock",
+            Field::OutputZip32Derivation => "output.zip32_derivation",
+            Field::UserAddress => "output.user_address",
+            Field::OutputProprietary => "output.proprietary",
+        }
+    }
+
+    /// Returns the first role that still needs this field of `action`, if any.
+    fn required_by(self, bundle: &Bundle, action: &Action) -> Option<&'static str> {
+        let proving_pending = bundle.zkproof.is_none();
+        let io_finalizing_pending = bundle.bsk.is_none();
+
+        match self {
+            Field::SpendValue
+            | Field::Rho
+            | Field::SpendRseed
+            | Field::Fvk
+            | Field::Witness
+            | Field::OutputValue
+            | Field::OutputRseed
+                if proving_pending =>
+            {
+                Some("Prover")
+            }
+            Field::SpendRecipient | Field::OutputRecipient => {
+                if proving_pending {
+                    Some("Prover")
+                } else if io_finalizing_pending && !bundle.flags.cross_address_enabled() {
+                    Some("IO Finalizer")
+                } else {
+                    None
+                }
+            }
+            Field::Rcv => {
+                if proving_pending {
+                    Some("Prover")
+                } else if io_finalizing_pending {
+                    Some("IO Finalizer")
+                } else {
+                    None
+                }
+            }
+            Field::Alpha => {
+                if proving_pending {
+                    Some("Prover")
+                } else if action.spend.spend_auth_sig.is_none() {
+                    if action.spend.dummy_sk.is_some() {
+                        Some("IO Finalizer")
+                    } else {
+                        Some("Signer")
+                    }
+                } else {
+                    None
+                }
+            }
+            Field::DummySk if io_finalizing_pending => Some("IO Finalizer"),
+            _ => None,
+        }
+    }
+
+    fn clear(self, action: &mut Action) {
+        match self {
+            Field::Rcv => action.rcv = None,
+            Field::SpendRecipient => action.spend.recipient = None,
+            Field::SpendValue => action.spend.value = None,
+            Field::Rho => action.spend.rho = None,
+            Field::SpendRseed => action.spend.rseed = None,
+            Field::Fvk => action.spend.fvk = None,
+            Field::Witness => action.spend.witness = None,
+            Field::Alpha => action.spend.alpha = None,
+            Field::SpendZip32Derivation => action.spend.zip32_derivation = None,
+            Field::DummySk => action.spend.dummy_sk = None,
+            Field::SpendProprietary => action.spend.proprietary.clear(),
+            Field::OutputRecipient => action.output.recipient = None,
+            Field::OutputValue => action.output.value = None,
+            Field::OutputRseed => action.output.rseed = None,
+            Field::Ock => action.output.ock = None,
+            Field::OutputZip32Derivation => action.output.zip32_derivation = None,
+            Field::UserAddress => action.output.user_address = None,
+            Field::OutputProprietary => action.output.proprietary.clear(),
+        }
+    }
+}
+
+/// A selection of fields to redact from an Orchard PCZT bundle.
+///
+/// Each `clear_*` method selects a field of every action in the bundle; use
+/// [`Redactor::redact_action_with`] to select fields of a single action.
+#[derive(Debug)]
+pub struct Redactor(Vec<(Option<usize>, Field)>);
+
+impl Redactor {
+    fn clear(mut self, field: Field) -> Self {
+        self.0.push((None, field));
+        self
+    }
+
+    /// Selects fields to redact from the action at the given index.
+    pub fn redact_action_with<F>(mut self, index: usize, f: F) -> Self
+    where
+        F: FnOnce(ActionRedactor) -> ActionRedactor,
+    {
+        let fields = f(ActionRedactor(Vec::new())).0;
+        self.0
+            .extend(fields.into_iter().map(|field| (Some(index), field)));
+        self
+    }
+
+    /// Redacts the value commitment randomness of every action.
+    pub fn clear_rcv(self) -> Self {
+        self.clear(Field::Rcv)
+    }
+
+    /// Redacts the recipient of every spent note.
+    pub fn clear_spend_recipients(self) -> Self {
+        self.clear(Field::SpendRecipient)
+    }
+
+    /// Redacts the value of every spent note.
+    pub fn clear_spend_values(self) -> Self {
+        self.clear(Field::SpendValue)
+    }
+
+    /// Redacts the `rho` of every spent note.
+    pub fn clear_rho(self) -> Self {
+        self.clear(Field::Rho)
+    }
+
+    /// Redacts the seed randomness of every spent note.
+    pub fn clear_spend_rseeds(self) -> Self {
+        self.clear(Field::SpendRseed)
+    }
+
+    /// Redacts the full viewing key of every spent note.
+    pub fn clear_fvks(self) -> Self {
+        self.clear(Field::Fvk)
+    }
+
+    /// Redacts the Merkle witness of every spent note.
+    pub fn clear_witnesses(self) -> Self {
+        self.clear(Field::Witness)
+    }
+
+    /// Redacts the spend authorization randomizer of every action.
+    pub fn clear_alpha(self) -> Self {
+        self.clear(Field::Alpha)
+    }
+
+    /// Redacts the ZIP 32 derivation path of every spent note.
+    pub fn clear_spend_zip32_derivations(self) -> Self {
+        self.clear(Field::SpendZip32Derivation)
+    }
+
+    /// Redacts the spending key of every dummy spent note.
+    pub fn clear_dummy_sks(self) -> Self {
+        self.clear(Field::DummySk)
+    }
+
+    /// Redacts the spend-specific proprietary fields of every action.
+    pub fn clear_spend_proprietary(self) -> Self {
+        self.clear(Field::SpendProprietary)
+    }
+
+    /// Redacts the recipient of every output.
+    pub fn clear_output_recipients(self) -> Self {
+        self.clear(Field::OutputRecipient)
+    }
+
+    /// Redacts the value of every output.
+    pub fn clear_output_values(self) -> Self {
+        self.clear(Field::OutputValue)
+    }
+
+    /// Redacts the seed randomness of every output.
+    pub fn clear_output_rseeds(self) -> Self {
+        self.clear(Field::OutputRseed)
+    }
+
+    /// Redacts the `ock` of every output.
+    pub fn clear_ocks(self) -> Self {
+        self.clear(Field::Ock)
+    }
+
+    /// Redacts the ZIP 32 derivation path of every output.
+    pub fn clear_output_zip32_derivations(self) -> Self {
+        self.clear(Field::OutputZip32Derivation)
+    }
+
+    /// Redacts the user-facing address of every output.
+    pub fn clear_user_addresses(self) -> Self {
+        self.clear(Field::UserAddress)
+    }
+
+    /// Redacts the output-specific proprietary fields of every action.
+    pub fn clear_output_proprietary(self) -> Self {
+        self.clear(Field::OutputProprietary)
+    }
+}
+
+/// A selection of fields to redact from a single Orchard PCZT action.
+#[derive(Debug)]
+pub struct ActionRedactor(Vec<Field>);
+
+impl ActionRedactor {
+    fn clear(mut self, field: Field) -> Self {
+        self.0.push(field);
+        self
+    }
+
+    /// Redacts the value commitment randomness.
+    pub fn clear_rcv(self) -> Self {
+        self.clear(Field::Rcv)
+    }
+
+    /// Redacts the recipient of the spent note.
+    pub fn clear_spend_recipient(self) -> Self {
+        self.clear(Field::SpendRecipient)
+    }
+
+    /// Redacts the value of the spent note.
+    pub fn clear_spend_value(self) -> Self {
+        self.clear(Field::SpendValue)
+    }
+
+    /// Redacts the `rho` of the spent note.
+    pub fn clear_rho(self) -> Self {
+        self.clear(Field::Rho)
+    }
+
+    /// Redacts the seed randomness of the spent note.
+    pub fn clear_spend_rseed(self) -> Self {
+        self.clear(Field::SpendRseed)
+    }
+
+    /// Redacts the full viewing key of the spent note.
+    pub fn clear_fvk(self) -> Self {
+        self.clear(Field::Fvk)
+    }
+
+    /// Redacts the Merkle witness of the spent note.
+    pub fn clear_witness(self) -> Self {
+        self.clear(Field::Witness)
+    }
+
+    /// Redacts the spend authorization randomizer.
+    pub fn clear_alpha(self) -> Self {
+        self.clear(Field::Alpha)
+    }
+
+    /// Redacts the ZIP 32 derivation path of the spent note.
+    pub fn clear_spend_zip32_derivation(self) -> Self {
+        self.clear(Field::SpendZip32Derivation)
+    }
+
+    /// Redacts the spending key of the spent note, if it is a dummy note.
+    pub fn clear_dummy_sk(self) -> Self {
+        self.clear(Field::DummySk)
+    }
+
+    /// Redacts the spend-specific proprietary fields.
+    pub fn clear_spend_proprietary(self) -> Self {
+        self.clear(Field::SpendProprietary)
+    }
+
+    /// Redacts the recipient of the output.
+    pub fn clear_output_recipient(self) -> Self {
+        self.clear(Field::OutputRecipient)
+    }
+
+    /// Redacts the value of the output.
+    pub fn clear_output_value(self) -> Self {
+        self.clear(Field::OutputValue)
+    }
+
+    /// Redacts the seed randomness of the output.
+    pub fn clear_output_rseed(self) -> Self {
+        self.clear(Field::OutputRseed)
+    }
+
+    /// Redacts the `ock` of the output.
+    pub fn clear_ock(self) -> Self {
+        self.clear(Field::Ock)
+    }
+
+    /// Redacts the ZIP 32 derivation path of the output.
+    pub fn clear_output_zip32_derivation(self) -> Self {
+        self.clear(Field::OutputZip32Derivation)
+    }
+
+    /// Redacts the user-facing address of the output.
+    pub fn clear_user_address(self) -> Self {
+        self.clear(Field::UserAddress)
+    }
+
+    /// Redacts the output-specific proprietary fields.
+    pub fn clear_output_proprietary(self) -> Self {
+        self.clear(Field::OutputProprietary)
+    }
+}
+
+/// Errors that can occur while redacting an Orchard bundle in a PCZT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RedactorError {
+    /// An out-of-bounds index was provided when selecting an action.
+    InvalidIndex,
+    /// A field selected for redaction is still needed by a later role.
+    FieldRequired {
+        /// The index of the action within the bundle.
+        index: usize,
+        /// The name of the field, qualified by `spend.` or `output.` where it belongs to
+        /// the action's spend or output.
+        field: &'static str,
+        /// The role that still needs the field.
+        role: &'static str,
+    },
+}
+
+impl fmt::Display for RedactorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedactorError::InvalidIndex => write!(f, "Action index is out-of-bounds"),
+            RedactorError::FieldRequired { index, field, role } => write!(
+                f,
+                "`{field}` of action {index} is still required by the {role} role"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RedactorError {}
@@ -0,0 +1,224 @@
+use core::fmt;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use getset::Getters;
+
+use super::{Bundle, VerifyError};
+use crate::{
+    keys::{FullViewingKey, Scope},
+    memo::MemoBytes,
+    value::{BalanceError, NoteValue, ValueSum},
+    Address,
+};
+
+impl Bundle {
+    /// Summarizes the bundle for display to the holder of `fvk` before signing.
+    ///
+    /// Every action's output is classified (see [`OutputKind`]) and reported with its
+    /// `user_address` and decrypted memo. The net value of the classified spends and
+    /// outputs is checked against the bundle's `value_sum`.
+    ///
+    /// This requires the `value` of every spend and output, and the `recipient` of every
+    /// output. It does not check the bundle's commitments or signatures; use
+    /// [`Bundle::verify_for_signing`] for that.
+    pub fn summarize(&self, fvk: &FullViewingKey) -> Result<BundleSummary, SummaryError> {
+        let mut outputs = Vec::with_capacity(self.actions.len());
+        let mut net_values = Vec::with_capacity(self.actions.len());
+        let mut spent = 0u64;
+
+        for (index, action) in self.actions.iter().enumerate() {
+            let in_action = |error| SummaryError::Action { index, error };
+            let (spend, output) = (&action.spend, &action.output);
+
+            let spend_value = spend
+                .value
+                .ok_or(VerifyError::MissingValue)
+                .map_err(in_action)?;
+            let value = output
+                .value
+                .ok_or(VerifyError::MissingValue)
+                .map_err(in_action)?;
+            let recipient = output
+                .recipient
+                .ok_or(VerifyError::MissingRecipient)
+                .map_err(in_action)?;
+
+            spent = spent
+                .checked_add(spend_value.inner())
+                .ok_or(SummaryError::ValueOverflow)?;
+            net_values.push(spend_value - value);
+
+            let scope = fvk.scope_for_address(&recipient);
+            let kind = if value.inner() != 0 {
+                match scope {
+                    Some(scope) => OutputKind::Change(scope),
+                    None => OutputKind::ExternalPayment,
+                }
+            } else if !self.flags.cross_address_enabled() && spend_value.inner() != 0 {
+                // Only an output to the spent note's own receiver is fabricated; any other
+                // zero-valued output is shown to the signer.
+                let fabricated = spend
+                    .recipient
+                    .is_some_and(|spent| spent.same_expanded_receiver(&recipient));
+                match (fabricated, scope) {
+                    (true, _) => OutputKind::Fabricated,
+                    (false, Some(scope)) => OutputKind::Change(scope),
+                    (false, None) => OutputKind::ExternalPayment,
+                }
+            } else if let Some(scope) = scope {
+                OutputKind::Change(scope)
+            } else if output.user_address.is_some() {
+                OutputKind::ExternalPayment
+            } else {
+                OutputKind::Dummy
+            };
+
+            // The fabricated output's ciphertext is randomized, and a dummy output's memo
+            // is meaningless.
+            let memo = match kind {
+                OutputKind::ExternalPayment | OutputKind::Change(_) => output
+                    .decrypt_enc_ciphertext(spend)
+                    .ok()
+                    .map(MemoBytes::from),
+                OutputKind::Dummy | OutputKind::Fabricated => None,
+            };
+
+            outputs.push(OutputSummary {
+                index,
+                kind,
+                recipient,
+                value,
+                user_address: output.user_address.clone(),
+                memo,
+            });
+        }
+
+        let net_value = net_values
+            .into_iter()
+            .sum::<Result<ValueSum, BalanceError>>()
+            .map_err(|_| SummaryError::ValueOverflow)?;
+        if net_value != self.value_sum {
+            return Err(SummaryError::ValueSumMismatch);
+        }
+
+        Ok(BundleSummary {
+            outputs,
+            spent,
+            value_balance: self.value_sum,
+        })
+    }
+}
+
+/// The classification of an action's output in a [`BundleSummary`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// A payment to an address not owned by the summarizing full viewing key.
+    ///
+    /// Zero-valued outputs to such addresses are only classified as payments if they
+    /// have a `user_address`, or are paired with a real spend in a bundle that disables
+    /// cross-address transfers; otherwise they are [`OutputKind::Dummy`].
+    ExternalPayment,
+    /// An output to an address owned by the summarizing full viewing key, in the given
+    /// scope.
+    Change(Scope),
+    /// A zero-valued output to an address not owned by the summarizing full viewing key,
+    /// such as the builder's padding outputs.
+    Dummy,
+    /// The zero-valued output that the builder pairs with a real spend in a bundle that
+    /// disables cross-address transfers. It is addressed to the spent note's own
+    /// receiver (which is checked against the spend's `recipient`), and its
+    /// `enc_ciphertext` may be randomized.
+    Fabricated,
+}
+
+/// A summary of an action's output, as shown to a user before signing.
+#[derive(Debug, Getters)]
+#[getset(get = "pub")]
+pub struct OutputSummary {
+    /// The index of the action within the bundle.
+    index: usize,
+    /// The classification of the output.
+    kind: OutputKind,
+    /// The address that will receive the output.
+    recipient: Address,
+    /// The value of the output.
+    value: NoteValue,
+    /// The user-facing address to which the output is being sent, if set by an Updater.
+    user_address: Option<String>,
+    /// The memo field of the output, if its `enc_ciphertext` could be decrypted.
+    ///
+    /// This is always `None` for [`OutputKind::Dummy`] and [`OutputKind::Fabricated`]
+    /// outputs.
+    memo: Option<MemoBytes>,
+}
+
+/// A human-readable summary of an Orchard PCZT bundle, produced by [`Bundle::summarize`].
+#[derive(Debug, Getters)]
+#[getset(get = "pub")]
+pub struct BundleSummary {
+    /// The summary of every action's output, in action order.
+    outputs: Vec<OutputSummary>,
+    /// The total value of the notes spent by the bundle.
+    spent: u64,
+    /// The net value leaving the Orchard pool. For a transaction with no other shielded
+    /// or transparent components, this is the fee.
+    value_balance: ValueSum,
+}
+
+impl BundleSummary {
+    /// Returns the total value of the [`OutputKind::ExternalPayment`] outputs, or `None`
+    /// if it overflows a `u64`.
+    pub fn total_sent(&self) -> Option<u64> {
+        self.total(|kind| kind == OutputKind::ExternalPayment)
+    }
+
+    /// Returns the total value of the [`OutputKind::Change`] outputs, or `None` if it
+    /// overflows a `u64`.
+    pub fn total_change(&self) -> Option<u64> {
+        self.total(|kind| matches!(kind, OutputKind::Change(_)))
+    }
+
+    fn total(&self, filter: impl Fn(OutputKind) -> bool) -> Option<u64> {
+        self.outputs
+            .iter()
+            .filter(|output| filter(output.kind))
+            .try_fold(0u64, |acc, output| acc.checked_add(output.value.inner()))
+    }
+}
+
+/// Errors that can occur while summarizing an Orchard PCZT bundle.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SummaryError {
+    /// A field of the action at `index` that the summary requires is missing.
+    Action {
+        /// The index of the action within the bundle.
+        index: usize,
+        /// The missing field.
+        error: VerifyError,
+    },
+    /// The net value of the spends and outputs does not match the bundle's `value_sum`.
+    ValueSumMismatch,
+    /// The bundle's values overflow.
+    ValueOverflow,
+}
+
+impl fmt::Display for SummaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SummaryError::Action { index, error } => {
+                write!(f, "Action {index} cannot be summarized: {error}")
+            }
+            SummaryError::ValueSumMismatch => write!(
+                f,
+                "The net value of the actions does not match the bundle's `value_sum`"
+            ),
+            SummaryError::ValueOverflow => write!(f, "The bundle's values overflow"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SummaryError {}
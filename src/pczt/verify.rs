@@ -1,10 +1,18 @@
 use core::fmt;
 
+use alloc::vec::Vec;
+
+use getset::Getters;
+use zcash_note_encryption::{
+    try_output_recovery_with_ock, try_output_recovery_with_pkd_esk, NoteEncryption,
+};
+
 use crate::{
     keys::{FullViewingKey, SpendValidatingKey},
     note::{ExtractedNoteCommitment, Rho},
-    value::ValueCommitment,
-    Note,
+    note_encryption::BundleDomain,
+    value::{NoteValue, ValueCommitment, ValueSum},
+    Address, Note,
 };
 
 impl super::Bundle {
@@ -43,6 +51,149 @@ impl super::Bundle {
 
         Ok(())
     }
+
+    /// Runs every check a Signer holding `fvk` can perform over all actions, and reports
+    /// the value flows of the bundle.
+    ///
+    /// For each action this verifies, where the fields needed are present:
+    /// - `cv_net`, if `rcv` is set;
+    /// - the spent note's `nullifier`, and `rk` (unless `alpha` has been redacted after
+    ///   signing);
+    /// - the output's `cmx` and `enc_ciphertext`, and its `out_ciphertext` if `ock` is
+    ///   set.
+    ///
+    /// The `value` of every spend and output is required, as is the output `recipient`
+    /// and `rseed`. In a bundle that disables cross-address transfers, a zero-valued
+    /// output without a `user_address` whose `enc_ciphertext` does not decrypt is
+    /// tolerated, as the builder randomizes that ciphertext for the output it pairs with
+    /// a real spend. In any other bundle, every `enc_ciphertext` must decrypt. The
+    /// bundle-level [`Self::verify_cross_address_restriction`] check is
+    /// also run.
+    ///
+    /// Spends whose `fvk` field is set to a key other than `fvk` are verified against
+    /// that key and reported separately. Outputs to addresses that `fvk` does not own must
+    /// each match a distinct entry of `expected_outputs`, and every entry of
+    /// `expected_outputs` must be matched.
+    pub fn verify_for_signing(
+        &self,
+        fvk: &FullViewingKey,
+        expected_outputs: &[(Address, NoteValue)],
+    ) -> Result<SigningReport, SigningVerifyError> {
+        self.verify_cross_address_restriction()
+            .map_err(SigningVerifyError::Bundle)?;
+
+        let mut report = SigningReport {
+            spent: 0,
+            other_spent: 0,
+            recipients: Vec::new(),
+            change: 0,
+            value_balance: self.value_sum,
+        };
+        let mut unmatched_outputs = expected_outputs.to_vec();
+
+        for (index, action) in self.actions.iter().enumerate() {
+            let in_action = |error| SigningVerifyError::Action { index, error };
+            let (spend, output) = (&action.spend, &action.output);
+
+            let spend_value = spend
+                .value
+                .ok_or(VerifyError::MissingValue)
+                .map_err(in_action)?;
+            let output_value = output
+                .value
+                .ok_or(VerifyError::MissingValue)
+                .map_err(in_action)?;
+
+            if action.rcv.is_some() {
+                action.verify_cv_net().map_err(in_action)?;
+            }
+
+            // Dummy spends are verified against their own `fvk` field.
+            let foreign_spend = spend_value.inner() != 0
+                && spend.fvk.as_ref().is_some_and(|spend_fvk| spend_fvk != fvk);
+            let expected_fvk = (!foreign_spend).then_some(fvk);
+            spend.verify_nullifier(expected_fvk).map_err(in_action)?;
+            if spend.alpha.is_some() || spend.spend_auth_sig.is_none() {
+                spend.verify_rk(expected_fvk).map_err(in_action)?;
+            }
+            let spent = if foreign_spend {
+                &mut report.other_spent
+            } else {
+                &mut report.spent
+            };
+            *spent = spent
+                .checked_add(spend_value.inner())
+                .ok_or(SigningVerifyError::ValueOverflow)?;
+
+            output.verify_note_commitment(spend).map_err(in_action)?;
+            match output.verify_enc_ciphertext(spend) {
+                Err(VerifyError::InvalidEncCiphertext)
+                    if output_value.inner() == 0
+                        && output.user_address.is_none()
+                        && !self.flags.cross_address_enabled() => {}
+                result => result.map_err(in_action)?,
+            }
+            if output.ock.is_some() {
+                output.verify_out_ciphertext(spend).map_err(in_action)?;
+            }
+
+            let recipient = output.recipient.expect("checked by verify_note_commitment");
+            if output_value.inner() == 0 {
+                // Dummy and padding outputs move no value.
+            } else if fvk.scope_for_address(&recipient).is_some() {
+                report.change = report
+                    .change
+                    .checked_add(output_value.inner())
+                    .ok_or(SigningVerifyError::ValueOverflow)?;
+            } else {
+                let expected = unmatched_outputs
+                    .iter()
+                    .position(|&expected| expected == (recipient, output_value))
+                    .ok_or(SigningVerifyError::UnexpectedOutput { index })?;
+                unmatched_outputs.swap_remove(expected);
+
+                match report
+                    .recipients
+                    .iter_mut()
+                    .find(|(address, _)| *address == recipient)
+                {
+                    Some((_, sent)) => {
+                        *sent = sent
+                            .checked_add(output_value.inner())
+                            .ok_or(SigningVerifyError::ValueOverflow)?
+                    }
+                    None => report.recipients.push((recipient, output_value.inner())),
+                }
+            }
+        }
+
+        match unmatched_outputs.first() {
+            Some(&(recipient, value)) => {
+                Err(SigningVerifyError::MissingExpectedOutput { recipient, value })
+            }
+            None => Ok(report),
+        }
+    }
+}
+
+/// The value flows of a PCZT bundle, as checked by [`Bundle::verify_for_signing`].
+///
+/// [`Bundle::verify_for_signing`]: super::Bundle::verify_for_signing
+#[derive(Debug, Getters)]
+#[getset(get = "pub")]
+pub struct SigningReport {
+    /// The total value of the spent notes received by the Signer's full viewing key.
+    spent: u64,
+    /// The total value of the spent notes received by other full viewing keys.
+    other_spent: u64,
+    /// The value sent to each recipient that the Signer's full viewing key does not own,
+    /// in order of first appearance in the bundle.
+    recipients: Vec<(Address, u64)>,
+    /// The total value of the outputs to addresses owned by the Signer's full viewing key.
+    change: u64,
+    /// The net value leaving the Orchard pool. For a transaction with no other shielded
+    /// or transparent components, this is the fee.
+    value_balance: ValueSum,
 }
 
 impl super::Action {
@@ -181,6 +332,93 @@ impl super::Output {
             Err(VerifyError::InvalidExtractedNoteCommitment)
         }
     }
+
+    /// Verifies that `out_ciphertext` is correctly encrypted under the `ock` field.
+    ///
+    /// This decrypts `out_ciphertext` to recover the output's `pk_d` and `esk`, checks that
+    /// they reproduce the ephemeral key and decrypt `enc_ciphertext` to a note matching
+    /// `cmx`, and that the recovered note is sent to `recipient` (and matches `value` and
+    /// `rseed`, if set).
+    ///
+    /// Requires that the following optional fields are set:
+    /// - `ock`
+    /// - `recipient`
+    ///
+    /// `spend` must be the Spend from the same Orchard action.
+    pub fn verify_out_ciphertext(&self, spend: &super::Spend) -> Result<(), VerifyError> {
+        let ock = self
+            .ock
+            .as_ref()
+            .ok_or(VerifyError::MissingOutgoingCipherKey)?;
+        let recipient = self.recipient.ok_or(VerifyError::MissingRecipient)?;
+
+        let domain = BundleDomain::for_rho(Rho::from_nf_old(spend.nullifier), self.note_version);
+        let (note, address, _) =
+            try_output_recovery_with_ock(&domain, ock, self, &self.encrypted_note.out_ciphertext)
+                .ok_or(VerifyError::InvalidOutCiphertext)?;
+
+        if address == recipient
+            && self.value.is_none_or(|value| value == note.value())
+            && self
+                .rseed
+                .is_none_or(|rseed| rseed.as_bytes() == note.rseed().as_bytes())
+        {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidOutCiphertext)
+        }
+    }
+
+    /// Verifies that `enc_ciphertext` is the encryption of the output note to `recipient`.
+    ///
+    /// This re-derives the note's ephemeral secret key from `rseed`, decrypts
+    /// `enc_ciphertext` with it, checks that the recovered note matches the note fields and
+    /// `cmx`, and then reconstructs `enc_ciphertext` from the recovered memo and compares
+    /// it against the transmitted ciphertext.
+    ///
+    /// Requires that the following optional fields are set:
+    /// - `recipient`
+    /// - `value`
+    /// - `rseed`
+    ///
+    /// `spend` must be the Spend from the same Orchard action.
+    pub fn verify_enc_ciphertext(&self, spend: &super::Spend) -> Result<(), VerifyError> {
+        self.decrypt_enc_ciphertext(spend).map(|_| ())
+    }
+
+    /// Performs the checks of [`Self::verify_enc_ciphertext`], returning the decrypted
+    /// memo.
+    pub(super) fn decrypt_enc_ciphertext(
+        &self,
+        spend: &super::Spend,
+    ) -> Result<[u8; 512], VerifyError> {
+        let note = Note::from_parts(
+            self.recipient.ok_or(VerifyError::MissingRecipient)?,
+            self.value.ok_or(VerifyError::MissingValue)?,
+            Rho::from_nf_old(spend.nullifier),
+            self.rseed.ok_or(VerifyError::MissingRandomSeed)?,
+            self.note_version,
+        )
+        .into_option()
+        .ok_or(VerifyError::InvalidOutputNote)?;
+
+        let domain = BundleDomain::for_rho(note.rho(), self.note_version);
+        let (decrypted, address, memo) =
+            try_output_recovery_with_pkd_esk(&domain, *note.recipient().pk_d(), note.esk(), self)
+                .ok_or(VerifyError::InvalidEncCiphertext)?;
+
+        let reconstructed =
+            NoteEncryption::<BundleDomain>::new(None, note, memo).encrypt_note_plaintext();
+        if address == note.recipient()
+            && decrypted.value() == note.value()
+            && decrypted.rseed().as_bytes() == note.rseed().as_bytes()
+            && reconstructed == self.encrypted_note.enc_ciphertext
+        {
+            Ok(memo)
+        } else {
+            Err(VerifyError::InvalidEncCiphertext)
+        }
+    }
 }
 
 /// Errors that can occur while verifying a PCZT bundle.
@@ -190,10 +428,16 @@ pub enum VerifyError {
     /// An action's output is addressed differently than its spent note, but the bundle's pool
     /// restriction disables cross-address transfers.
     DisallowedCrossAddressTransfer,
+    /// The output's `enc_ciphertext` is not the encryption of the output note to its
+    /// recipient.
+    InvalidEncCiphertext,
     /// The output note's components do not produce the expected `cmx`.
     InvalidExtractedNoteCommitment,
     /// The spent note's components do not produce the expected `nullifier`.
     InvalidNullifier,
+    /// The output's `out_ciphertext` does not decrypt under `ock` to the output's
+    /// recipient and note.
+    InvalidOutCiphertext,
     /// The output note's components do not produce a valid note commitment.
     InvalidOutputNote,
     /// The Spend's FVK and `alpha` do not produce the expected `rk`.
@@ -206,6 +450,8 @@ pub enum VerifyError {
     MismatchedFullViewingKey,
     /// Dummy notes must have their `fvk` field set in order to be verified.
     MissingFullViewingKey,
+    /// `out_ciphertext` verification requires `ock` to be set.
+    MissingOutgoingCipherKey,
     /// `nullifier` verification requires `rseed` to be set.
     MissingRandomSeed,
     /// Verification requires `recipient` to be set.
@@ -230,10 +476,19 @@ impl fmt::Display for VerifyError {
                 "an action outputs to a different expanded receiver than it spends from, but the \
                  bundle disables cross-address transfers"
             ),
+            VerifyError::InvalidEncCiphertext => {
+                write!(f, "`enc_ciphertext` doesn't encrypt the output note")
+            }
             VerifyError::InvalidExtractedNoteCommitment => {
                 write!(f, "output note doesn't match `cmx`")
             }
             VerifyError::InvalidNullifier => write!(f, "spent note doesn't match `nullifier`"),
+            VerifyError::InvalidOutCiphertext => {
+                write!(
+                    f,
+                    "`out_ciphertext` doesn't decrypt to the output note under `ock`"
+                )
+            }
             VerifyError::InvalidOutputNote => write!(f, "invalid output note"),
             VerifyError::InvalidRandomizedVerificationKey => {
                 write!(f, "spend's `fvk` and `alpha` do not match `rk`")
@@ -246,6 +501,9 @@ impl fmt::Display for VerifyError {
                 write!(f, "Provided full viewing key doesn't match the `fvk` field")
             }
             VerifyError::MissingFullViewingKey => write!(f, "`fvk` missing for dummy note"),
+            VerifyError::MissingOutgoingCipherKey => {
+                write!(f, "`ock` missing for `out_ciphertext` verification")
+            }
             VerifyError::MissingRandomSeed => {
                 write!(f, "`rseed` missing for `nullifier` verification")
             }
@@ -265,3 +523,59 @@ impl fmt::Display for VerifyError {
 
 #[cfg(feature = "std")]
 impl std::error::Error for VerifyError {}
+
+/// Errors that can occur in [`Bundle::verify_for_signing`].
+///
+/// [`Bundle::verify_for_signing`]: super::Bundle::verify_for_signing
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SigningVerifyError {
+    /// A bundle-level check failed.
+    Bundle(VerifyError),
+    /// A check of the action at `index` failed.
+    Action {
+        /// The index of the action within the bundle.
+        index: usize,
+        /// The failed check.
+        error: VerifyError,
+    },
+    /// The output of the action at `index` pays an address the Signer does not own, and
+    /// does not match any expected output.
+    UnexpectedOutput {
+        /// The index of the action within the bundle.
+        index: usize,
+    },
+    /// No output of the bundle matches an expected output.
+    MissingExpectedOutput {
+        /// The recipient of the expected output.
+        recipient: Address,
+        /// The value of the expected output.
+        value: NoteValue,
+    },
+    /// The reported values overflow a `u64`.
+    ValueOverflow,
+}
+
+impl fmt::Display for SigningVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningVerifyError::Bundle(e) => write!(f, "Bundle verification failed: {e}"),
+            SigningVerifyError::Action { index, error } => {
+                write!(f, "Verification of action {index} failed: {error}")
+            }
+            SigningVerifyError::UnexpectedOutput { index } => write!(
+                f,
+                "The output of action {index} pays an unexpected external recipient"
+            ),
+            SigningVerifyError::MissingExpectedOutput { value, .. } => write!(
+                f,
+                "No output pays an expected recipient {} zatoshis",
+                value.inner()
+            ),
+            SigningVerifyError::ValueOverflow => write!(f, "The bundle's values overflow"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SigningVerifyError {}
@@ -1,4 +1,6 @@
 pub(crate) mod commitment_tree;
+#[cfg(feature = "encoding")]
+pub(crate) mod f4jumble;
 pub(crate) mod keys;
 pub(crate) mod merkle_path;
 pub(crate) mod note_encryption;
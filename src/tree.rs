@@ -11,7 +11,7 @@ use crate::{
     note::commitment::ExtractedNoteCommitment,
 };
 
-use incrementalmerkletree::{Hashable, Level};
+use incrementalmerkletree::{frontier::Frontier, Hashable, Level};
 use pasta_curves::pallas;
 use sinsemilla::HashDomain;
 
@@ -23,6 +23,16 @@ use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 use subtle::{Choice, ConditionallySelectable, CtOption};
 
+mod encoding;
+#[cfg(feature = "shardtree")]
+mod note_commitment_tree;
+pub use encoding::{
+    read_frontier, read_legacy_frontier, read_subtree_roots, write_frontier, write_subtree_roots,
+};
+#[cfg(feature = "shardtree")]
+#[cfg_attr(docsrs, doc(cfg(feature = "shardtree")))]
+pub use note_commitment_tree::{NoteCommitmentTree, TreeError};
+
 // The uncommitted leaf is defined as pallas::Base(2).
 // <https://zips.z.cash/protocol/protocol.pdf#thmuncommittedorchard>
 lazy_static! {
@@ -73,6 +83,11 @@ impl Anchor {
         self.0
     }
 
+    /// Returns the anchor of the note commitment tree with the given frontier.
+    pub fn from_frontier(frontier: &Frontier<MerkleHashOrchard, 32>) -> Anchor {
+        frontier.root().into()
+    }
+
     /// Parses an Orchard anchor from a byte encoding.
     pub fn from_bytes(bytes: [u8; 32]) -> CtOption<Anchor> {
         pallas::Base::from_repr(bytes).map(Anchor)
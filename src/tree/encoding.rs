@@ -0,0 +1,365 @@
+//! Encodings of Orchard note commitment tree data, as used by `zcashd` and light-client
+//! servers.
+//!
+//! - A frontier is encoded in `zcashd`'s `OrchardMerkleFrontier` layout: an optional
+//!   (non-empty) frontier given by its 64-bit position, its leaf (split into the left and
+//!   right leaves of the last leaf pair) and its remaining ommers. Light-client servers
+//!   (via `z_gettreestate`) instead send the legacy `IncrementalMerkleTree` layout, which
+//!   [`read_legacy_frontier`] parses.
+//! - A list of roots of the 2^16-leaf subtrees, as returned by `z_getsubtreesbyindex`, is
+//!   encoded as a compactSize count followed by the roots.
+//! - A [`MerklePath`] is encoded in `zcashd`'s witness path layout: a compactSize vector
+//!   of the 32 sibling hashes from the root down to the leaf, each itself encoded as a
+//!   compactSize-prefixed 32-byte vector, followed by the 64-bit little-endian position.
+//!
+//! Every hash is checked to be a canonical encoding with [`MerkleHashOrchard::from_bytes`].
+
+use alloc::vec::Vec;
+
+use corez::io::{self, Read, Write};
+use incrementalmerkletree::{
+    frontier::{Frontier, NonEmptyFrontier},
+    Hashable, Position,
+};
+
+use super::{MerkleHashOrchard, MerklePath};
+use crate::{
+    bundle::encoding::{read_array, read_compact_size, write_compact_size},
+    constants::MERKLE_DEPTH_ORCHARD,
+};
+
+/// The number of roots of 2^16-leaf subtrees in an Orchard note commitment tree.
+const MAX_SUBTREE_ROOTS: usize = 1 << 16;
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_hash<R: Read>(reader: R) -> io::Result<MerkleHashOrchard> {
+    Option::from(MerkleHashOrchard::from_bytes(&read_array(reader)?))
+        .ok_or_else(|| invalid_data("invalid Orchard note commitment tree node"))
+}
+
+fn read_optional_hash<R: Read>(mut reader: R) -> io::Result<Option<MerkleHashOrchard>> {
+    match read_array::<_, 1>(&mut reader)?[0] {
+        0 => Ok(None),
+        1 => read_hash(reader).map(Some),
+        _ => Err(invalid_data("invalid optional value flag")),
+    }
+}
+
+fn read_hashes<R: Read>(mut reader: R, max: usize) -> io::Result<Vec<MerkleHashOrchard>> {
+    let len = read_compact_size(&mut reader)?;
+    if len > max {
+        return Err(invalid_data("too many Orchard note commitment tree nodes"));
+    }
+    (0..len).map(|_| read_hash(&mut reader)).collect()
+}
+
+fn write_hashes<W: Write>(mut writer: W, hashes: &[MerkleHashOrchard]) -> io::Result<()> {
+    write_compact_size(&mut writer, hashes.len())?;
+    hashes
+        .iter()
+        .try_for_each(|hash| writer.write_all(&hash.to_bytes()))
+}
+
+/// Builds a frontier from its parts, rejecting parts that do not describe a frontier of
+/// the Orchard note commitment tree.
+fn frontier_from_parts(
+    position: u64,
+    leaf: MerkleHashOrchard,
+    ommers: Vec<MerkleHashOrchard>,
+) -> io::Result<Frontier<MerkleHashOrchard, 32>> {
+    NonEmptyFrontier::from_parts(Position::from(position), leaf, ommers)
+        .ok()
+        .and_then(|frontier| Frontier::try_from(frontier).ok())
+        .ok_or_else(|| invalid_data("invalid Orchard note commitment tree frontier"))
+}
+
+/// Writes `frontier` in `zcashd`'s `OrchardMerkleFrontier` encoding.
+pub fn write_frontier<W: Write>(
+    mut writer: W,
+    frontier: &Frontier<MerkleHashOrchard, 32>,
+) -> io::Result<()> {
+    let Some(frontier) = frontier.value() else {
+        return writer.write_all(&[0]);
+    };
+
+    writer.write_all(&[1])?;
+    writer.write_all(&u64::from(frontier.position()).to_le_bytes())?;
+    if frontier.position().is_right_child() {
+        // The first ommer is the leaf's left sibling, which is written as the left leaf.
+        let (left, ommers) = frontier
+            .ommers()
+            .split_first()
+            .expect("a right child has a left sibling");
+        writer.write_all(&left.to_bytes())?;
+        writer.write_all(&[1])?;
+        writer.write_all(&frontier.leaf().to_bytes())?;
+        write_hashes(writer, ommers)
+    } else {
+        writer.write_all(&frontier.leaf().to_bytes())?;
+        writer.write_all(&[0])?;
+        write_hashes(writer, frontier.ommers())
+    }
+}
+
+/// Reads a frontier in `zcashd`'s `OrchardMerkleFrontier` encoding.
+pub fn read_frontier<R: Read>(mut reader: R) -> io::Result<Frontier<MerkleHashOrchard, 32>> {
+    match read_array::<_, 1>(&mut reader)?[0] {
+        0 => return Ok(Frontier::empty()),
+        1 => (),
+        _ => return Err(invalid_data("invalid optional value flag")),
+    }
+
+    let position = u64::from_le_bytes(read_array(&mut reader)?);
+    let left = read_hash(&mut reader)?;
+    let right = read_optional_hash(&mut reader)?;
+    let mut ommers = read_hashes(&mut reader, MERKLE_DEPTH_ORCHARD)?;
+    let leaf = match right {
+        Some(right) => {
+            ommers.insert(0, left);
+            right
+        }
+        None => left,
+    };
+    frontier_from_parts(position, leaf, ommers)
+}
+
+/// Reads a frontier in the legacy `IncrementalMerkleTree` encoding that `zcashd` returns
+/// from `z_gettreestate`: the optional left and right leaves of the last leaf pair, then a
+/// compactSize vector of optional ommers at each level above the leaves.
+pub fn read_legacy_frontier<R: Read>(mut reader: R) -> io::Result<Frontier<MerkleHashOrchard, 32>> {
+    let left = read_optional_hash(&mut reader)?;
+    let right = read_optional_hash(&mut reader)?;
+    let num_parents = read_compact_size(&mut reader)?;
+    if num_parents >= MERKLE_DEPTH_ORCHARD {
+        return Err(invalid_data("too many Orchard note commitment tree nodes"));
+    }
+    let parents = (0..num_parents)
+        .map(|_| read_optional_hash(&mut reader))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // The occupied levels are the binary digits of the tree size.
+    let size = parents
+        .iter()
+        .enumerate()
+        .filter(|(_, parent)| parent.is_some())
+        .fold(
+            u64::from(right.is_some()) + u64::from(left.is_some()),
+            |size, (i, _)| size + (1 << (i + 1)),
+        );
+    let ommers = parents.into_iter().flatten();
+    let (leaf, ommers) = match (left, right) {
+        (None, None) if size == 0 => return Ok(Frontier::empty()),
+        (Some(left), None) => (left, ommers.collect()),
+        (Some(left), Some(right)) => (right, Some(left).into_iter().chain(ommers).collect()),
+        _ => return Err(invalid_data("invalid Orchard note commitment tree")),
+    };
+    frontier_from_parts(size - 1, leaf, ommers)
+}
+
+/// Writes a list of roots of consecutive 2^16-leaf subtrees of the note commitment tree.
+///
+/// # Errors
+///
+/// Returns an error of kind [`io::ErrorKind::InvalidInput`] if there are more roots than
+/// the tree has subtrees, in which case nothing is written.
+pub fn write_subtree_roots<W: Write>(writer: W, roots: &[MerkleHashOrchard]) -> io::Result<()> {
+    if roots.len() > MAX_SUBTREE_ROOTS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many Orchard subtree roots",
+        ));
+    }
+    write_hashes(writer, roots)
+}
+
+/// Reads a list of roots of consecutive 2^16-leaf subtrees of the note commitment tree.
+pub fn read_subtree_roots<R: Read>(reader: R) -> io::Result<Vec<MerkleHashOrchard>> {
+    read_hashes(reader, MAX_SUBTREE_ROOTS)
+}
+
+impl MerklePath {
+    /// Writes this Merkle path in `zcashd`'s witness path encoding.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_compact_size(&mut writer, MERKLE_DEPTH_ORCHARD)?;
+        for sibling in self.auth_path.iter().rev() {
+            write_compact_size(&mut writer, 32)?;
+            writer.write_all(&sibling.to_bytes())?;
+        }
+        writer.write_all(&u64::from(self.position).to_le_bytes())
+    }
+
+    /// Reads a Merkle path in `zcashd`'s witness path encoding.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        if read_compact_size(&mut reader)? != MERKLE_DEPTH_ORCHARD {
+            return Err(invalid_data("invalid Orchard Merkle path depth"));
+        }
+        let mut auth_path = [MerkleHashOrchard::empty_leaf(); MERKLE_DEPTH_ORCHARD];
+        for sibling in auth_path.iter_mut().rev() {
+            if read_compact_size(&mut reader)? != 32 {
+                return Err(invalid_data("invalid Orchard Merkle path node length"));
+            }
+            *sibling = read_hash(&mut reader)?;
+        }
+        let position = u32::try_from(u64::from_le_bytes(read_array(&mut reader)?))
+            .map_err(|_| invalid_data("Orchard Merkle path position out of range"))?;
+        Ok(MerklePath::from_parts(position, auth_path))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use incrementalmerkletree::frontier::Frontier;
+
+    use super::{
+        read_frontier, read_legacy_frontier, read_subtree_roots, write_frontier,
+        write_subtree_roots,
+    };
+    #[cfg(feature = "shardtree")]
+    use crate::{
+        note::ExtractedNoteCommitment,
+        tree::{MerklePath, NoteCommitmentTree},
+    };
+    use crate::{
+        test_vectors::merkle_path,
+        tree::{Anchor, MerkleHashOrchard},
+    };
+
+    fn leaves() -> Vec<MerkleHashOrchard> {
+        merkle_path::test_vectors()
+            .last()
+            .unwrap()
+            .leaves
+            .iter()
+            .map(|leaf| MerkleHashOrchard::from_bytes(leaf).unwrap())
+            .collect()
+    }
+
+    /// Encodes `frontier` in the legacy `IncrementalMerkleTree` layout.
+    fn legacy_encoding(frontier: &Frontier<MerkleHashOrchard, 32>) -> Vec<u8> {
+        let mut bytes = vec![];
+        let Some(frontier) = frontier.value() else {
+            return vec![0, 0, 0];
+        };
+        let position = u64::from(frontier.position());
+        let mut ommers = frontier.ommers().iter();
+        let optional = |bytes: &mut Vec<u8>, hash: Option<&MerkleHashOrchard>| match hash {
+            Some(hash) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&hash.to_bytes());
+            }
+            None => bytes.push(0),
+        };
+        if position & 1 == 1 {
+            optional(&mut bytes, ommers.next());
+            optional(&mut bytes, Some(frontier.leaf()));
+        } else {
+            optional(&mut bytes, Some(frontier.leaf()));
+            optional(&mut bytes, None);
+        }
+        let num_parents = (64 - position.leading_zeros()).saturating_sub(1);
+        bytes.push(num_parents as u8);
+        for level in 1..=num_parents {
+            optional(
+                &mut bytes,
+                (position & (1 << level) != 0).then(|| ommers.next().unwrap()),
+            );
+        }
+        bytes
+    }
+
+    #[test]
+    fn frontier_round_trip() {
+        let mut frontier = Frontier::<MerkleHashOrchard, 32>::empty();
+        let mut bytes = vec![];
+        write_frontier(&mut bytes, &frontier).unwrap();
+        assert_eq!(bytes, [0]);
+        assert_eq!(read_frontier(&bytes[..]).unwrap(), frontier);
+        assert_eq!(read_legacy_frontier(&[0, 0, 0][..]).unwrap(), frontier);
+        assert_eq!(Anchor::from_frontier(&frontier), Anchor::empty_tree());
+
+        for leaf in leaves() {
+            frontier.append(leaf);
+
+            let mut bytes = vec![];
+            write_frontier(&mut bytes, &frontier).unwrap();
+            assert_eq!(read_frontier(&bytes[..]).unwrap(), frontier);
+            assert_eq!(
+                read_legacy_frontier(&legacy_encoding(&frontier)[..]).unwrap(),
+                frontier
+            );
+
+            // A non-canonical hash is rejected.
+            bytes[40] = 0xff;
+            assert!(read_frontier(&bytes[..]).is_err());
+        }
+    }
+
+    #[cfg(feature = "shardtree")]
+    #[test]
+    fn frontier_anchor_matches_tree() {
+        let mut frontier = Frontier::<MerkleHashOrchard, 32>::empty();
+        let mut tree = NoteCommitmentTree::new(100);
+        for (i, leaf) in leaves().into_iter().enumerate() {
+            frontier.append(leaf);
+            let cmx = ExtractedNoteCommitment::from_bytes(&leaf.to_bytes()).unwrap();
+            tree.append(cmx, false).unwrap();
+            tree.checkpoint(i as u32);
+            assert_eq!(
+                Anchor::from_frontier(&frontier),
+                tree.anchor(i as u32).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn subtree_roots_round_trip() {
+        let roots = leaves();
+        let mut bytes = vec![];
+        write_subtree_roots(&mut bytes, &roots).unwrap();
+        assert_eq!(bytes.len(), 1 + 32 * roots.len());
+        assert_eq!(read_subtree_roots(&bytes[..]).unwrap(), roots);
+
+        let too_many = vec![roots[0]; (1 << 16) + 1];
+        assert!(write_subtree_roots(&mut vec![], &too_many).is_err());
+        let mut bytes = vec![];
+        super::write_hashes(&mut bytes, &too_many).unwrap();
+        assert!(read_subtree_roots(&bytes[..]).is_err());
+    }
+
+    #[cfg(feature = "shardtree")]
+    #[test]
+    fn merkle_path_round_trip() {
+        let leaves = leaves();
+        let mut tree = NoteCommitmentTree::new(1);
+        for leaf in &leaves {
+            let cmx = ExtractedNoteCommitment::from_bytes(&leaf.to_bytes()).unwrap();
+            tree.append(cmx, true).unwrap();
+        }
+        tree.checkpoint(0);
+
+        for position in [0, 5, 15] {
+            let path = tree.witness(position, 0).unwrap();
+            let mut bytes = vec![];
+            path.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 1 + 32 * 33 + 8);
+            // The sibling of the leaf is written last.
+            assert_eq!(
+                bytes[bytes.len() - 40..bytes.len() - 8],
+                path.auth_path()[0].to_bytes()
+            );
+
+            let read = MerklePath::read(&bytes[..]).unwrap();
+            assert_eq!(read.position(), position);
+            assert_eq!(read.auth_path(), path.auth_path());
+
+            let mut too_far = bytes.clone();
+            too_far[bytes.len() - 4] = 1;
+            assert!(MerklePath::read(&too_far[..]).is_err());
+        }
+    }
+}
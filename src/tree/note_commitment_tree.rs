@@ -0,0 +1,326 @@
+//! An in-memory note commitment tree that retains witnesses for a wallet's notes.
+
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{convert::Infallible, fmt};
+
+use incrementalmerkletree::{Position, Retention};
+use shardtree::{
+    error::{InsertionError, QueryError, ShardTreeError},
+    store::{memory::MemoryShardStore, ShardStore},
+    ShardTree,
+};
+
+use super::{Anchor, MerkleHashOrchard, MerklePath};
+use crate::{bundle::Authorization, note::ExtractedNoteCommitment, Bundle};
+
+/// The level of the shards into which the tree is divided.
+const SHARD_HEIGHT: u8 = 16;
+
+/// An error type for the kinds of errors that can occur while updating or querying a
+/// [`NoteCommitmentTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TreeError {
+    /// The tree already contains 2^32 note commitments.
+    TreeFull,
+    /// No checkpoint exists at the given block height, either because none was created or
+    /// because it has been pruned or rewound.
+    UnknownCheckpoint(u32),
+    /// The note commitment at the given position is not in the tree as of the requested
+    /// checkpoint, or was not marked, so its witness was not retained.
+    NotWitnessed(u32),
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::TreeFull => f.write_str("Note commitment tree is full"),
+            TreeError::UnknownCheckpoint(height) => {
+                write!(f, "No checkpoint exists at height {height}")
+            }
+            TreeError::NotWitnessed(position) => {
+                write!(f, "No witness is retained for position {position}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// Maps an error from the underlying tree, attributing query failures to `position`.
+fn tree_error(e: ShardTreeError<Infallible>, position: u32) -> TreeError {
+    match e {
+        ShardTreeError::Insert(InsertionError::TreeFull) => TreeError::TreeFull,
+        ShardTreeError::Query(QueryError::NotContained(_) | QueryError::TreeIncomplete(_)) => {
+            TreeError::NotWitnessed(position)
+        }
+        // Leaves are only ever appended, and the in-memory store cannot fail.
+        e => unreachable!("unexpected note commitment tree error: {e}"),
+    }
+}
+
+/// An in-memory note commitment tree for a single value pool, which retains the witnesses
+/// of the notes marked as belonging to a wallet.
+///
+/// Note commitments are appended in block order, and a checkpoint is added at the end of
+/// each block, identified by the block height. The tree can compute the anchor as of each
+/// retained checkpoint, and a [`MerklePath`] from each marked note to that anchor that
+/// [`Builder::add_spend`] accepts. Only the most recent checkpoints (up to the number given
+/// to [`NoteCommitmentTree::new`]) are retained, and a chain reorganization is handled by
+/// rewinding to the checkpoint at the fork point.
+///
+/// Orchard and Ironwood notes are committed to separate trees, so a wallet keeps one
+/// `NoteCommitmentTree` per value pool.
+///
+/// [`Builder::add_spend`]: crate::builder::Builder::add_spend
+#[derive(Debug)]
+pub struct NoteCommitmentTree {
+    inner: ShardTree<MemoryShardStore<MerkleHashOrchard, u32>, 32, SHARD_HEIGHT>,
+    size: u64,
+}
+
+impl NoteCommitmentTree {
+    /// Constructs an empty tree that retains at most `max_checkpoints` checkpoints.
+    pub fn new(max_checkpoints: usize) -> Self {
+        NoteCommitmentTree {
+            inner: ShardTree::new(MemoryShardStore::empty(), max_checkpoints),
+            size: 0,
+        }
+    }
+
+    /// Returns the number of note commitments in the tree.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Appends a note commitment to the tree, marking it if `marked` is `true` so that its
+    /// witness is retained.
+    ///
+    /// Returns the position of the note commitment in the tree.
+    pub fn append(&mut self, cmx: ExtractedNoteCommitment, marked: bool) -> Result<u32, TreeError> {
+        let position = u32::try_from(self.size).map_err(|_| TreeError::TreeFull)?;
+        let retention = if marked {
+            Retention::Marked
+        } else {
+            Retention::Ephemeral
+        };
+        self.inner
+            .append(MerkleHashOrchard::from_cmx(&cmx), retention)
+            .map_err(|e| tree_error(e, position))?;
+        self.size += 1;
+        Ok(position)
+    }
+
+    /// Appends the note commitments of every action in `bundle`, in order, marking those
+    /// of the actions for which `is_mine` returns `true`.
+    ///
+    /// Returns the action index and tree position of each marked note commitment.
+    pub fn append_bundle<T: Authorization, V>(
+        &mut self,
+        bundle: &Bundle<T, V>,
+        mut is_mine: impl FnMut(usize) -> bool,
+    ) -> Result<Vec<(usize, u32)>, TreeError> {
+        let mut marked = vec![];
+        for (action_idx, action) in bundle.actions().iter().enumerate() {
+            let mine = is_mine(action_idx);
+            let position = self.append(*action.cmx(), mine)?;
+            if mine {
+                marked.push((action_idx, position));
+            }
+        }
+        Ok(marked)
+    }
+
+    /// Adds a checkpoint for the block at `height`, at the current end of the tree.
+    ///
+    /// Returns `false` (and adds no checkpoint) if `height` is not greater than the height
+    /// of the latest checkpoint. If the tree now holds more checkpoints than it retains,
+    /// the oldest is pruned, along with the data that only it needed.
+    pub fn checkpoint(&mut self, height: u32) -> bool {
+        self.inner
+            .checkpoint(height)
+            .unwrap_or_else(|e| unreachable!("checkpointing cannot fail: {e}"))
+    }
+
+    /// Returns the height of the latest checkpoint, if any.
+    pub fn latest_checkpoint(&self) -> Option<u32> {
+        self.inner
+            .store()
+            .max_checkpoint_id()
+            .unwrap_or_else(|e| match e {})
+    }
+
+    /// Rewinds the tree to the checkpoint at `height`, removing every note commitment
+    /// appended, and every checkpoint added, after it.
+    ///
+    /// This is used to roll back blocks that were removed by a chain reorganization. The
+    /// checkpoint at `height` itself is retained.
+    pub fn rewind(&mut self, height: u32) -> Result<(), TreeError> {
+        if !self
+            .inner
+            .truncate_to_checkpoint(&height)
+            .unwrap_or_else(|e| unreachable!("truncation cannot fail: {e}"))
+        {
+            return Err(TreeError::UnknownCheckpoint(height));
+        }
+        self.size = self
+            .inner
+            .max_leaf_position(Some(0))
+            .unwrap_or_else(|e| unreachable!("the latest checkpoint exists: {e}"))
+            .map_or(0, |position| u64::from(position) + 1);
+        Ok(())
+    }
+
+    /// Returns the anchor of the tree as of the checkpoint at `height`.
+    pub fn anchor(&self, height: u32) -> Result<Anchor, TreeError> {
+        self.inner
+            .root_at_checkpoint_id(&height)
+            .map_err(|e| tree_error(e, 0))?
+            .map(Anchor::from)
+            .ok_or(TreeError::UnknownCheckpoint(height))
+    }
+
+    /// Returns the Merkle path from the marked note commitment at `position` to the anchor
+    /// as of the checkpoint at `height`.
+    pub fn witness(&self, position: u32, height: u32) -> Result<MerklePath, TreeError> {
+        self.inner
+            .witness_at_checkpoint_id(Position::from(u64::from(position)), &height)
+            .map_err(|e| tree_error(e, position))?
+            .map(MerklePath::from)
+            .ok_or(TreeError::UnknownCheckpoint(height))
+    }
+
+    /// Returns the positions of the marked note commitments, including those whose marks
+    /// have been removed as of a checkpoint that has not yet been pruned.
+    pub fn marked_positions(&self) -> BTreeSet<u32> {
+        self.inner
+            .marked_positions()
+            .unwrap_or_else(|e| match e {
+                ShardTreeError::Storage(e) => match e {},
+                e => unreachable!("reading marks cannot fail: {e}"),
+            })
+            .into_iter()
+            .map(|position| u64::from(position) as u32)
+            .collect()
+    }
+
+    /// Removes the mark from the note commitment at `position`, for a note that was spent
+    /// in the block at `height`.
+    ///
+    /// The witness remains available until the checkpoint at `height` is pruned, so that
+    /// it can be restored if the tree is rewound to before the spend. Returns `false` if
+    /// the note commitment at `position` is not marked.
+    pub fn remove_mark(&mut self, position: u32, height: u32) -> bool {
+        self.inner
+            .remove_mark(Position::from(u64::from(position)), Some(&height))
+            .unwrap_or_else(|e| unreachable!("removing a mark cannot fail: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use incrementalmerkletree::Hashable;
+
+    use super::{NoteCommitmentTree, TreeError};
+    use crate::{
+        constants::MERKLE_DEPTH_ORCHARD,
+        note::ExtractedNoteCommitment,
+        test_vectors::{commitment_tree, merkle_path},
+        tree::{Anchor, MerkleHashOrchard, EMPTY_ROOTS},
+    };
+
+    fn cmx(bytes: &[u8; 32]) -> ExtractedNoteCommitment {
+        ExtractedNoteCommitment::from_bytes(bytes).unwrap()
+    }
+
+    /// The anchor of a depth-32 tree whose leaves all lie in its leftmost depth-4 subtree,
+    /// which has the given root.
+    fn extend_root(root: [u8; 32]) -> Anchor {
+        let root = (4..MERKLE_DEPTH_ORCHARD).fold(
+            MerkleHashOrchard::from_bytes(&root).unwrap(),
+            |node, level| {
+                MerkleHashOrchard::combine((level as u8).into(), &node, &EMPTY_ROOTS[level])
+            },
+        );
+        root.into()
+    }
+
+    #[test]
+    fn witnesses_match_test_vectors() {
+        let mut tree = NoteCommitmentTree::new(100);
+
+        // An empty checkpoint has the empty root.
+        assert!(tree.checkpoint(999));
+        let empty_roots = commitment_tree::test_vectors().empty_roots;
+        assert_eq!(
+            tree.anchor(999).unwrap().to_bytes(),
+            empty_roots[MERKLE_DEPTH_ORCHARD]
+        );
+        assert_eq!(tree.anchor(999), Ok(Anchor::empty_tree()));
+
+        // The Merkle path vectors are for a depth-4 tree, which is the leftmost subtree of
+        // the depth-32 tree.
+        let tvs = merkle_path::test_vectors();
+        for (i, tv) in tvs.iter().enumerate() {
+            let height = 1000 + i as u32;
+            assert_eq!(tree.append(cmx(&tv.leaves[i]), true), Ok(i as u32));
+            assert!(tree.checkpoint(height));
+
+            let anchor = tree.anchor(height).unwrap();
+            assert_eq!(anchor, extend_root(tv.root));
+
+            for j in 0..=i {
+                let path = tree.witness(j as u32, height).unwrap();
+                assert_eq!(path.position(), j as u32);
+                let auth_path = path.auth_path().map(|node| node.to_bytes());
+                assert_eq!(auth_path[..4], tv.paths[j]);
+                assert!(auth_path[4..]
+                    .iter()
+                    .zip(&EMPTY_ROOTS[4..])
+                    .all(|(node, empty)| *node == empty.to_bytes()));
+                // This is the check that `Builder::add_spend` makes.
+                assert_eq!(path.root(cmx(&tv.leaves[j])), anchor);
+            }
+        }
+        assert_eq!(tree.size(), tvs.len() as u64);
+        assert_eq!(tree.latest_checkpoint(), Some(1000 + tvs.len() as u32 - 1));
+    }
+
+    #[test]
+    fn rewind_restores_earlier_state() {
+        let tvs = merkle_path::test_vectors();
+        let leaves = &tvs.last().unwrap().leaves;
+        let mut tree = NoteCommitmentTree::new(10);
+
+        assert_eq!(tree.append(cmx(&leaves[0]), true), Ok(0));
+        assert_eq!(tree.append(cmx(&leaves[1]), false), Ok(1));
+        assert!(tree.checkpoint(1));
+        let anchor = tree.anchor(1).unwrap();
+
+        assert_eq!(tree.append(cmx(&leaves[2]), true), Ok(2));
+        assert!(tree.checkpoint(2));
+        assert!(!tree.checkpoint(2));
+        // The mark is only dropped once the checkpoint for the spend is pruned.
+        assert!(tree.remove_mark(0, 2));
+        assert_eq!(tree.marked_positions(), [0, 2].into_iter().collect());
+
+        // Roll back block 2.
+        assert_eq!(tree.rewind(2 + 1), Err(TreeError::UnknownCheckpoint(3)));
+        tree.rewind(1).unwrap();
+        assert_eq!(tree.size(), 2);
+        assert_eq!(tree.latest_checkpoint(), Some(1));
+        assert_eq!(tree.anchor(1), Ok(anchor));
+        assert_eq!(tree.anchor(2), Err(TreeError::UnknownCheckpoint(2)));
+        assert_eq!(tree.marked_positions(), [0].into_iter().collect());
+        assert_eq!(tree.witness(0, 1).unwrap().root(cmx(&leaves[0])), anchor);
+        assert_eq!(tree.witness(2, 1).unwrap_err(), TreeError::NotWitnessed(2));
+
+        // Replay a different block 2.
+        assert_eq!(tree.append(cmx(&leaves[3]), true), Ok(2));
+        assert!(tree.checkpoint(2));
+        assert_eq!(
+            tree.witness(2, 2).unwrap().root(cmx(&leaves[3])),
+            tree.anchor(2).unwrap()
+        );
+    }
+}
@@ -5,17 +5,19 @@ use core::fmt;
 use blake2b_simd::Params as Blake2bParams;
 use subtle::{Choice, ConstantTimeEq, CtOption};
 use zcash_spec::VariableLengthSlice;
-use zip32::{
-    hardened_only::{self, HardenedOnlyKey},
-    ChainCode,
-};
+use zip32::hardened_only::{self, Context, HardenedOnlyKey};
 
 use crate::{
     keys::{FullViewingKey, SpendingKey},
     spec::PrfExpand,
 };
 
-pub use zip32::ChildIndex;
+mod application;
+mod path;
+pub use application::{ArbitraryKey, RegisteredKey};
+pub use path::{DerivationPath, PathError};
+
+pub use zip32::{registered::PathElement, ChainCode, ChildIndex};
 
 const ZIP32_ORCHARD_PERSONALIZATION: &[u8; 16] = b"ZcashIP32Orchard";
 const ZIP32_ORCHARD_FVFP_PERSONALIZATION: &[u8; 16] = b"ZcashOrchardFVFP";
@@ -30,19 +32,36 @@ pub enum Error {
     InvalidChildIndex(u32),
     /// Derivation depth would exceed 255
     MaxDerivationDepth,
+    /// A context string was empty or longer than 252 bytes
+    InvalidContextString,
+    /// A seed of the given length was shorter than 32 bytes or longer than 252 bytes
+    InvalidSeedLength(usize),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Seed produced invalid spending key.")
+        match self {
+            Error::InvalidContextString => {
+                write!(f, "Context string must be between 1 and 252 bytes.")
+            }
+            Error::InvalidSeedLength(len) => {
+                write!(f, "Seed of {len} bytes must be between 32 and 252 bytes.")
+            }
+            _ => write!(f, "Seed produced invalid spending key."),
+        }
     }
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
-/// An Orchard full viewing key fingerprint
-struct FvkFingerprint([u8; 32]);
+/// An Orchard full viewing key fingerprint.
+///
+/// Defined in [ZIP32: Orchard key identifiers][orchardkeyidentifiers].
+///
+/// [orchardkeyidentifiers]: https://zips.z.cash/zip-0032#orchard-key-identifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FvkFingerprint([u8; 32]);
 
 impl From<&FullViewingKey> for FvkFingerprint {
     fn from(fvk: &FullViewingKey) -> Self {
@@ -57,12 +76,18 @@ impl From<&FullViewingKey> for FvkFingerprint {
     }
 }
 
-/// An Orchard full viewing key tag
-#[derive(Clone, Copy, Debug, PartialEq)]
-struct FvkTag([u8; 4]);
+/// An Orchard full viewing key tag: the first four bytes of its [`FvkFingerprint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FvkTag([u8; 4]);
 
 impl FvkFingerprint {
-    fn tag(&self) -> FvkTag {
+    /// Returns the raw bytes of the fingerprint.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the tag of the fingerprint.
+    pub fn tag(&self) -> FvkTag {
         let mut tag = [0u8; 4];
         tag.copy_from_slice(&self.0[..4]);
         FvkTag(tag)
@@ -70,7 +95,18 @@ impl FvkFingerprint {
 }
 
 impl FvkTag {
-    fn master() -> Self {
+    /// Constructs a tag from its raw bytes.
+    pub fn from_bytes(tag: [u8; 4]) -> Self {
+        FvkTag(tag)
+    }
+
+    /// Returns the raw bytes of the tag.
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+
+    /// The tag used as the parent tag of a master key.
+    pub fn master() -> Self {
         FvkTag([0u8; 4])
     }
 }
@@ -139,11 +175,12 @@ impl hardened_only::Context for Orchard {
 ///
 /// [orchardextendedkeys]: https://zips.z.cash/zip-0032#orchard-extended-keys
 #[derive(Debug, Clone)]
-pub(crate) struct ExtendedSpendingKey {
+pub struct ExtendedSpendingKey {
     depth: u8,
     parent_fvk_tag: FvkTag,
     child_index: KeyIndex,
-    inner: HardenedOnlyKey<Orchard>,
+    sk: SpendingKey,
+    chain_code: ChainCode,
 }
 
 impl ConstantTimeEq for ExtendedSpendingKey {
@@ -151,7 +188,8 @@ impl ConstantTimeEq for ExtendedSpendingKey {
         self.depth.ct_eq(&rhs.depth)
             & self.parent_fvk_tag.0.ct_eq(&rhs.parent_fvk_tag.0)
             & self.child_index.ct_eq(&rhs.child_index)
-            & self.inner.ct_eq(&rhs.inner)
+            & self.sk.ct_eq(&rhs.sk)
+            & self.chain_code.ct_eq(&rhs.chain_code)
     }
 }
 
@@ -160,9 +198,7 @@ impl ExtendedSpendingKey {
     /// Returns the spending key of the child key corresponding to
     /// the path derived from the master key
     ///
-    /// # Panics
-    ///
-    /// Panics if seed results in invalid spending key.
+    /// Returns the errors of [`Self::master`] and [`Self::derive_child`].
     pub fn from_path(seed: &[u8], path: &[ChildIndex]) -> Result<Self, Error> {
         let mut xsk = Self::master(seed)?;
         for i in path {
@@ -177,23 +213,24 @@ impl ExtendedSpendingKey {
     ///
     /// [orchardmasterkey]: https://zips.z.cash/zip-0032#orchard-master-key-generation
     ///
-    /// # Panics
-    ///
-    /// Panics if the seed is shorter than 32 bytes or longer than 252 bytes.
-    fn master(seed: &[u8]) -> Result<Self, Error> {
-        let m_orchard = HardenedOnlyKey::master(&[seed]);
-
-        let sk = SpendingKey::from_bytes(*m_orchard.parts().0);
-        if sk.is_none().into() {
-            return Err(Error::InvalidSpendingKey);
+    /// Returns [`Error::InvalidSeedLength`] if the seed is shorter than 32 bytes or longer
+    /// than 252 bytes.
+    pub fn master(seed: &[u8]) -> Result<Self, Error> {
+        if !(32..=252).contains(&seed.len()) {
+            return Err(Error::InvalidSeedLength(seed.len()));
         }
 
+        let m_orchard = HardenedOnlyKey::<Orchard>::master(&[seed]);
+        let (sk, chain_code) = m_orchard.parts();
+        let sk = Option::from(SpendingKey::from_bytes(*sk)).ok_or(Error::InvalidSpendingKey)?;
+
         // For the master extended spending key, depth is 0, parent_fvk_tag is 4 zero bytes, and i is 0.
         Ok(Self {
             depth: 0,
-            parent_fvk_tag: FvkTag([0; 4]),
+            parent_fvk_tag: FvkTag::master(),
             child_index: KeyIndex::master(),
-            inner: m_orchard,
+            sk,
+            chain_code: *chain_code,
         })
     }
 
@@ -204,34 +241,103 @@ impl ExtendedSpendingKey {
     /// [orchardchildkey]: https://zips.z.cash/zip-0032#orchard-child-key-derivation
     ///
     /// Discards index if it results in an invalid sk
-    fn derive_child(&self, index: ChildIndex) -> Result<Self, Error> {
+    pub fn derive_child(&self, index: ChildIndex) -> Result<Self, Error> {
         let depth = self.depth.checked_add(1).ok_or(Error::MaxDerivationDepth)?;
 
-        let child_i = self.inner.derive_child(index);
-
-        let sk = SpendingKey::from_bytes(*child_i.parts().0);
-        if sk.is_none().into() {
-            return Err(Error::InvalidSpendingKey);
-        }
+        // This is the hardened-only child derivation with an empty tag. It is computed
+        // here rather than by `HardenedOnlyKey`, which cannot be reconstructed from the
+        // parts of a decoded key.
+        let i = Orchard::CKD_DOMAIN.with(
+            self.chain_code.as_bytes(),
+            self.sk.to_bytes(),
+            &index.index().to_le_bytes(),
+            &[0],
+            &[],
+        );
+        let (sk, chain_code) = i.split_at(32);
+        let sk = Option::from(SpendingKey::from_bytes(sk.try_into().unwrap()))
+            .ok_or(Error::InvalidSpendingKey)?;
 
         let fvk: FullViewingKey = self.into();
 
         Ok(Self {
             depth,
-            parent_fvk_tag: FvkFingerprint::from(&fvk).tag(),
+            parent_fvk_tag: fvk.tag(),
             child_index: KeyIndex::child(index),
-            inner: child_i,
+            sk,
+            chain_code: ChainCode::new(chain_code.try_into().unwrap()),
         })
     }
 
     /// Returns sk of this ExtendedSpendingKey.
     pub fn sk(&self) -> SpendingKey {
-        SpendingKey::from_bytes(*self.inner.parts().0).expect("checked during derivation")
+        self.sk
     }
 
     /// Returns the chain code for this ExtendedSpendingKey.
-    fn chain_code(&self) -> &ChainCode {
-        self.inner.parts().1
+    pub fn chain_code(&self) -> &ChainCode {
+        &self.chain_code
+    }
+
+    /// Returns the depth of this key in the derivation tree, which is 0 for a master key.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the tag of the full viewing key of this key's parent, which is
+    /// [`FvkTag::master`] for a master key.
+    pub fn parent_fvk_tag(&self) -> FvkTag {
+        self.parent_fvk_tag
+    }
+
+    /// Returns the index at which this key was derived from its parent, or `None` for a
+    /// master key.
+    pub fn child_index(&self) -> Option<ChildIndex> {
+        self.child_index.0.into()
+    }
+
+    /// Serializes the extended spending key as specified in
+    /// [ZIP32: Orchard extended keys][orchardextendedkeys]: the depth, the parent full
+    /// viewing key tag, the 32-bit little-endian child index, the chain code, and the
+    /// spending key.
+    ///
+    /// ZIP 32 defines no string encoding of Orchard extended spending keys, so this
+    /// binary encoding is the only one.
+    ///
+    /// [orchardextendedkeys]: https://zips.z.cash/zip-0032#orchard-extended-keys
+    pub fn to_bytes(&self) -> [u8; 73] {
+        let mut bytes = [0; 73];
+        bytes[0] = self.depth;
+        bytes[1..5].copy_from_slice(&self.parent_fvk_tag.0);
+        bytes[5..9].copy_from_slice(&self.child_index.index().to_le_bytes());
+        bytes[9..41].copy_from_slice(self.chain_code.as_bytes());
+        bytes[41..].copy_from_slice(self.sk.to_bytes());
+        bytes
+    }
+
+    /// Parses an extended spending key from the encoding produced by
+    /// [`ExtendedSpendingKey::to_bytes`].
+    ///
+    /// Returns `None` if the spending key is invalid, if a master key (at depth 0) has a
+    /// non-zero child index or parent tag, or if a child key's index is not hardened.
+    pub fn from_bytes(bytes: &[u8; 73]) -> Option<Self> {
+        let depth = bytes[0];
+        let parent_fvk_tag = FvkTag(bytes[1..5].try_into().unwrap());
+        let child_index =
+            KeyIndex::new(depth, u32::from_le_bytes(bytes[5..9].try_into().unwrap()))?;
+        if depth == 0 && parent_fvk_tag != FvkTag::master() {
+            return None;
+        }
+        let chain_code = ChainCode::new(bytes[9..41].try_into().unwrap());
+        let sk = SpendingKey::from_bytes(bytes[41..].try_into().unwrap()).into_option()?;
+
+        Some(Self {
+            depth,
+            parent_fvk_tag,
+            child_index,
+            sk,
+            chain_code,
+        })
     }
 }
 
@@ -250,6 +356,22 @@ mod tests {
         assert!(xsk_5.is_ok());
     }
 
+    #[test]
+    fn master_rejects_invalid_seed_lengths() {
+        for len in [31, 253] {
+            let seed = vec![0; len];
+            assert_eq!(
+                ExtendedSpendingKey::master(&seed).err(),
+                Some(Error::InvalidSeedLength(len))
+            );
+            assert_eq!(
+                ExtendedSpendingKey::from_path(&seed, &[ChildIndex::hardened(5)]).err(),
+                Some(Error::InvalidSeedLength(len))
+            );
+        }
+        assert!(ExtendedSpendingKey::master(&[0; 252]).is_ok());
+    }
+
     #[test]
     fn derive_child_depth_overflow() {
         let seed = [0; 32];
@@ -322,6 +444,44 @@ mod tests {
 
             let fvk: FullViewingKey = (&xsk.sk()).into();
             assert_eq!(FvkFingerprint::from(&fvk).0, tv.fp);
+            assert_eq!(fvk.fingerprint().as_bytes(), &tv.fp);
+            assert_eq!(fvk.tag().as_bytes(), &tv.fp[..4]);
+
+            assert_eq!(xsk.to_bytes(), tv.xsk);
+            let decoded = ExtendedSpendingKey::from_bytes(&tv.xsk).unwrap();
+            assert!(bool::from(decoded.ct_eq(xsk)));
         }
+
+        // Children of a decoded key are derived as from the original key.
+        let decoded = ExtendedSpendingKey::from_bytes(&xsks[2].to_bytes()).unwrap();
+        assert!(bool::from(
+            decoded.derive_child(i3h).unwrap().ct_eq(&xsks[3])
+        ));
+        assert_eq!(xsks[0].child_index(), None);
+        assert_eq!(xsks[3].child_index(), Some(i3h));
+        assert_eq!(
+            xsks[3].parent_fvk_tag(),
+            FullViewingKey::from(&xsks[2]).tag()
+        );
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        let seed = [0; 32];
+        let xsk_m = ExtendedSpendingKey::master(&seed).unwrap();
+        let xsk_5h = xsk_m.derive_child(ChildIndex::hardened(5)).unwrap();
+
+        // A master key must have a zero child index and parent tag.
+        let mut bytes = xsk_m.to_bytes();
+        bytes[5] = 1;
+        assert!(ExtendedSpendingKey::from_bytes(&bytes).is_none());
+        let mut bytes = xsk_m.to_bytes();
+        bytes[1] = 1;
+        assert!(ExtendedSpendingKey::from_bytes(&bytes).is_none());
+
+        // A child key's index must be hardened.
+        let mut bytes = xsk_5h.to_bytes();
+        bytes[5..9].copy_from_slice(&5u32.to_le_bytes());
+        assert!(ExtendedSpendingKey::from_bytes(&bytes).is_none());
     }
 }
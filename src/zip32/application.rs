@@ -0,0 +1,234 @@
+//! ZIP 32 key derivation for application-specific contexts.
+//!
+//! These key trees are unrelated to the Orchard tree rooted at
+//! [`ExtendedSpendingKey::master`](super::ExtendedSpendingKey::master), even for the same
+//! seed. Their keys can be used as raw 32-byte secrets, or as Orchard spending keys for
+//! funds that are held apart from the wallet's accounts.
+//!
+//! The derivations are those of the `zip32` crate; these types add checks of the input
+//! lengths and conversion to a [`SpendingKey`].
+
+use core::fmt;
+
+use zip32::{arbitrary, registered, registered::PathElement, ChainCode, ChildIndex};
+
+use super::Error;
+use crate::keys::SpendingKey;
+
+/// Checks that the context string and seed have lengths that ZIP 32 allows.
+fn check_lengths(context_string: &[u8], seed: &[u8]) -> Result<(), Error> {
+    if !(1..=252).contains(&context_string.len()) {
+        Err(Error::InvalidContextString)
+    } else if !(32..=252).contains(&seed.len()) {
+        Err(Error::InvalidSeedLength(seed.len()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Interprets a raw 32-byte key as an Orchard spending key.
+fn to_spending_key(data: &[u8; 32]) -> Result<SpendingKey, Error> {
+    SpendingKey::from_bytes(*data)
+        .into_option()
+        .ok_or(Error::InvalidSpendingKey)
+}
+
+/// An extended secret key for an application-specific context.
+///
+/// Defined in [ZIP 32: Ad-hoc (formerly arbitrary) key derivation][arbkd].
+///
+/// This scheme is deprecated in ZIP 32, and is supported for existing applications that
+/// derive keys with it. New applications should use [`RegisteredKey`].
+///
+/// [arbkd]: https://zips.z.cash/zip-0032#specification-ad-hoc-key-derivation-deprecated
+pub struct ArbitraryKey(arbitrary::SecretKey);
+
+impl fmt::Debug for ArbitraryKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArbitraryKey").finish_non_exhaustive()
+    }
+}
+
+impl ArbitraryKey {
+    /// Derives the key at the given path from the given seed.
+    ///
+    /// `context_string` identifies the application in which the key will be used, and
+    /// must be globally unique.
+    ///
+    /// Returns [`Error::InvalidContextString`] if the context string is empty or longer
+    /// than 252 bytes, and [`Error::InvalidSeedLength`] if the seed is shorter than 32
+    /// bytes or longer than 252 bytes.
+    pub fn from_path(
+        context_string: &[u8],
+        seed: &[u8],
+        path: &[ChildIndex],
+    ) -> Result<Self, Error> {
+        check_lengths(context_string, seed)?;
+        Ok(ArbitraryKey(arbitrary::SecretKey::from_path(
+            context_string,
+            seed,
+            path,
+        )))
+    }
+
+    /// Returns the raw 32-byte key.
+    pub fn data(&self) -> &[u8; 32] {
+        self.0.data()
+    }
+
+    /// Returns the chain code of this key.
+    pub fn chain_code(&self) -> &ChainCode {
+        self.0.chain_code()
+    }
+
+    /// Interprets this key as an Orchard spending key.
+    ///
+    /// Returns [`Error::InvalidSpendingKey`] if the key is not a valid spending key, in
+    /// which case the caller should move on to another path.
+    pub fn to_spending_key(&self) -> Result<SpendingKey, Error> {
+        to_spending_key(self.data())
+    }
+}
+
+/// An extended secret key in the subtree of an application protocol defined by a ZIP.
+///
+/// Each path element below the subtree root may carry a tag, such as an application
+/// identifier or a device name, in addition to its index.
+///
+/// Defined in [ZIP 32: Registered key derivation][regkd].
+///
+/// [regkd]: https://zips.z.cash/zip-0032#specification-registered-key-derivation
+pub struct RegisteredKey(registered::SecretKey);
+
+impl Clone for RegisteredKey {
+    fn clone(&self) -> Self {
+        RegisteredKey(registered::SecretKey::from_parts(
+            *self.data(),
+            *self.chain_code(),
+        ))
+    }
+}
+
+impl fmt::Debug for RegisteredKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegisteredKey").finish_non_exhaustive()
+    }
+}
+
+impl RegisteredKey {
+    /// Derives the key at the given subpath of the subtree `m_context/zip_number'` from
+    /// the given seed.
+    ///
+    /// Returns the same errors as [`ArbitraryKey::from_path`].
+    pub fn from_subpath(
+        context_string: &[u8],
+        seed: &[u8],
+        zip_number: u16,
+        subpath: &[PathElement<'_>],
+    ) -> Result<Self, Error> {
+        check_lengths(context_string, seed)?;
+        Ok(RegisteredKey(
+            registered::SecretKey::from_subpath(context_string, seed, zip_number, subpath)
+                .expect("the lengths were checked"),
+        ))
+    }
+
+    /// Derives a child key from this key at the given index and (possibly empty) tag.
+    pub fn derive_child_with_tag(&self, index: ChildIndex, tag: &[u8]) -> Self {
+        RegisteredKey(self.0.derive_child_with_tag(index, tag))
+    }
+
+    /// Returns the raw 32-byte key.
+    pub fn data(&self) -> &[u8; 32] {
+        self.0.data()
+    }
+
+    /// Returns the chain code of this key.
+    pub fn chain_code(&self) -> &ChainCode {
+        self.0.chain_code()
+    }
+
+    /// Interprets this key as an Orchard spending key.
+    ///
+    /// Returns [`Error::InvalidSpendingKey`] if the key is not a valid spending key.
+    pub fn to_spending_key(&self) -> Result<SpendingKey, Error> {
+        to_spending_key(self.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use zip32::{registered::PathElement, ChildIndex};
+
+    use super::{ArbitraryKey, RegisteredKey};
+    use crate::zip32::Error;
+
+    #[test]
+    fn arbitrary_test_vectors() {
+        for tv in crate::test_vectors::zip32::ARBITRARY_TEST_VECTORS {
+            let path = tv
+                .path
+                .iter()
+                .map(|i| ChildIndex::from_index(*i).unwrap())
+                .collect::<Vec<_>>();
+            let key = ArbitraryKey::from_path(tv.context_string, &tv.seed, &path).unwrap();
+            assert_eq!(key.data(), &tv.sk);
+            assert_eq!(key.chain_code().as_bytes(), &tv.c);
+
+            if let Ok(sk) = key.to_spending_key() {
+                assert_eq!(sk.to_bytes(), &tv.sk);
+            }
+        }
+    }
+
+    #[test]
+    fn registered_test_vectors() {
+        for tv in crate::test_vectors::zip32::REGISTERED_TEST_VECTORS {
+            let subpath = tv
+                .subpath
+                .iter()
+                .map(|(i, tag)| PathElement::new(ChildIndex::from_index(*i).unwrap(), tag))
+                .collect::<Vec<_>>();
+            let key =
+                RegisteredKey::from_subpath(tv.context_string, &tv.seed, tv.zip_number, &subpath)
+                    .unwrap();
+            assert_eq!(key.data(), &tv.sk);
+            assert_eq!(key.chain_code().as_bytes(), &tv.c);
+
+            // Deriving the last element from its parent gives the same key.
+            if let Some((last, parent)) = subpath.split_last() {
+                let parent =
+                    RegisteredKey::from_subpath(tv.context_string, &tv.seed, tv.zip_number, parent)
+                        .unwrap();
+                let child = parent
+                    .clone()
+                    .derive_child_with_tag(last.child_index(), last.tag());
+                assert_eq!(child.data(), &tv.sk);
+                assert_eq!(child.chain_code(), key.chain_code());
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        let seed = [0; 32];
+        assert_eq!(
+            ArbitraryKey::from_path(b"", &seed, &[]).unwrap_err(),
+            Error::InvalidContextString
+        );
+        assert_eq!(
+            ArbitraryKey::from_path(&[b'a'; 253], &seed, &[]).unwrap_err(),
+            Error::InvalidContextString
+        );
+        assert_eq!(
+            ArbitraryKey::from_path(b"Zcash test vectors", &seed[..31], &[]).unwrap_err(),
+            Error::InvalidSeedLength(31)
+        );
+        assert_eq!(
+            RegisteredKey::from_subpath(b"Zcash test vectors", &[0; 253], 1, &[]).unwrap_err(),
+            Error::InvalidSeedLength(253)
+        );
+    }
+}
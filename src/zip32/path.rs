@@ -0,0 +1,259 @@
+//! ZIP 32 derivation paths.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use zip32::{AccountId, ChildIndex};
+
+use super::Error;
+
+/// The purpose index of the `m/32'/coin_type'/account'` paths defined in ZIP 32.
+const ZIP32_PURPOSE: ChildIndex = ChildIndex::hardened(32);
+
+/// An error type for the kinds of errors that can occur while parsing a
+/// [`DerivationPath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathError {
+    /// The path does not start with `m`.
+    MissingRoot,
+    /// The given component is not a decimal index below 2^31 with an optional hardened
+    /// marker (`'` or `h`).
+    InvalidComponent(String),
+    /// The component with the given index is not hardened. Orchard only supports hardened
+    /// derivation.
+    NonHardened(u32),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::MissingRoot => write!(f, "Derivation path must start with \"m\""),
+            PathError::InvalidComponent(c) => {
+                write!(f, "Invalid derivation path component \"{c}\"")
+            }
+            PathError::NonHardened(i) => write!(
+                f,
+                "Derivation path component {i} is not hardened, which Orchard requires"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PathError {}
+
+/// A ZIP 32 derivation path of hardened child indices, such as `m/32'/133'/0'`.
+///
+/// A path is parsed from and displayed as a string of `/`-separated components after the
+/// root `m`. Each component is a decimal index followed by `'` (or `h`), since Orchard only
+/// supports hardened derivation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DerivationPath(Vec<ChildIndex>);
+
+impl DerivationPath {
+    /// Returns the path `m/32'/coin_type'/account'` of a ZIP 32 account.
+    ///
+    /// Returns [`Error::InvalidChildIndex`] if `coin_type` is 2^31 or greater.
+    pub fn for_account(coin_type: u32, account: AccountId) -> Result<Self, Error> {
+        if coin_type >= (1 << 31) {
+            return Err(Error::InvalidChildIndex(coin_type));
+        }
+        Ok(DerivationPath(alloc::vec![
+            ZIP32_PURPOSE,
+            ChildIndex::hardened(coin_type),
+            account.into(),
+        ]))
+    }
+
+    /// Returns the indices of this path.
+    pub fn as_slice(&self) -> &[ChildIndex] {
+        &self.0
+    }
+
+    /// Returns the coin type of a path that starts with `m/32'/coin_type'`, or `None` for
+    /// any other path.
+    pub fn coin_type(&self) -> Option<u32> {
+        match &self.0[..] {
+            [purpose, coin_type, ..] if *purpose == ZIP32_PURPOSE => {
+                Some(coin_type.index() - (1 << 31))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the account of a path that starts with `m/32'/coin_type'/account'`, or
+    /// `None` for any other path.
+    ///
+    /// The path may continue below the account, for example to a sub-account key.
+    pub fn account(&self) -> Option<AccountId> {
+        match &self.0[..] {
+            [purpose, _, account, ..] if *purpose == ZIP32_PURPOSE => Some(
+                AccountId::try_from(account.index() - (1 << 31))
+                    .expect("ChildIndex only supports hardened indices"),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this path is exactly `m/32'/coin_type'/account'` for some coin type
+    /// and account.
+    pub fn is_account_path(&self) -> bool {
+        self.0.len() == 3 && self.account().is_some()
+    }
+}
+
+impl From<Vec<ChildIndex>> for DerivationPath {
+    fn from(path: Vec<ChildIndex>) -> Self {
+        DerivationPath(path)
+    }
+}
+
+impl From<DerivationPath> for Vec<ChildIndex> {
+    fn from(path: DerivationPath) -> Self {
+        path.0
+    }
+}
+
+impl AsRef<[ChildIndex]> for DerivationPath {
+    fn as_ref(&self) -> &[ChildIndex] {
+        &self.0
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for i in &self.0 {
+            write!(f, "/{}'", i.index() - (1 << 31))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, PathError> {
+        let mut components = s.split('/');
+        if components.next() != Some("m") {
+            return Err(PathError::MissingRoot);
+        }
+
+        components
+            .map(|c| {
+                let invalid = || PathError::InvalidComponent(c.into());
+                let (index, hardened) = match c.strip_suffix(['\'', 'h']) {
+                    Some(index) => (index, true),
+                    None => (c, false),
+                };
+                if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                let index: u32 = index.parse().map_err(|_| invalid())?;
+                if index >= (1 << 31) {
+                    Err(invalid())
+                } else if !hardened {
+                    Err(PathError::NonHardened(index))
+                } else {
+                    Ok(ChildIndex::hardened(index))
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+
+    use zip32::{AccountId, ChildIndex};
+
+    use super::{DerivationPath, PathError};
+    use crate::pczt::Zip32Derivation;
+
+    #[test]
+    fn parse_and_display() {
+        let path: DerivationPath = "m/32'/133'/0'".parse().unwrap();
+        assert_eq!(
+            path.as_slice(),
+            [
+                ChildIndex::hardened(32),
+                ChildIndex::hardened(133),
+                ChildIndex::hardened(0)
+            ]
+        );
+        assert_eq!(path.to_string(), "m/32'/133'/0'");
+        assert_eq!(
+            path,
+            DerivationPath::for_account(133, AccountId::ZERO).unwrap()
+        );
+        assert_eq!("m/32h/133h/0h".parse(), Ok(path));
+
+        let root: DerivationPath = "m".parse().unwrap();
+        assert!(root.as_slice().is_empty());
+        assert_eq!(root.to_string(), "m");
+
+        let max = "m/2147483647'".parse::<DerivationPath>().unwrap();
+        assert_eq!(max.as_slice(), [ChildIndex::PRIVATE_USE]);
+    }
+
+    #[test]
+    fn invalid_paths_are_rejected() {
+        assert_eq!("".parse::<DerivationPath>(), Err(PathError::MissingRoot));
+        assert_eq!(
+            "32'/133'".parse::<DerivationPath>(),
+            Err(PathError::MissingRoot)
+        );
+        assert_eq!(
+            "M/32'".parse::<DerivationPath>(),
+            Err(PathError::MissingRoot)
+        );
+        assert_eq!(
+            "m/32'/133/0'".parse::<DerivationPath>(),
+            Err(PathError::NonHardened(133))
+        );
+        for invalid in ["", "'", "+1'", "-1'", "1''", "0x1'", " 1'", "2147483648'"] {
+            assert_eq!(
+                ["m/", invalid].concat().parse::<DerivationPath>(),
+                Err(PathError::InvalidComponent(invalid.into()))
+            );
+        }
+        assert_eq!(
+            "m/32'/".parse::<DerivationPath>(),
+            Err(PathError::InvalidComponent("".into()))
+        );
+        assert_eq!(
+            DerivationPath::for_account(1 << 31, AccountId::ZERO),
+            Err(crate::zip32::Error::InvalidChildIndex(1 << 31))
+        );
+    }
+
+    #[test]
+    fn account_components() {
+        let account = AccountId::try_from(7).unwrap();
+        let path = DerivationPath::for_account(1, account).unwrap();
+        assert_eq!(path.coin_type(), Some(1));
+        assert_eq!(path.account(), Some(account));
+        assert!(path.is_account_path());
+
+        let derivation = Zip32Derivation::from_path([1; 32], path.clone());
+        assert_eq!(derivation.derivation_path(), path.as_slice());
+        assert_eq!(derivation.path(), path);
+
+        // A sub-account path keeps its account.
+        let mut sub_account = Vec::from(path);
+        sub_account.push(ChildIndex::hardened(3));
+        let sub_account = DerivationPath::from(sub_account);
+        assert_eq!(sub_account.account(), Some(account));
+        assert!(!sub_account.is_account_path());
+
+        let other: DerivationPath = "m/44'/133'/0'".parse().unwrap();
+        assert_eq!(other.coin_type(), None);
+        assert_eq!(other.account(), None);
+        let short: DerivationPath = "m/32'/133'".parse().unwrap();
+        assert_eq!(short.coin_type(), Some(133));
+        assert_eq!(short.account(), None);
+    }
+}
@@ -3,14 +3,18 @@
 use incrementalmerkletree::{Hashable, Marking, Retention};
 use orchard::{
     builder::{Builder, BundleType},
-    bundle::{Authorized, BatchValidator, BundleVersion, Flags, TxVersion},
+    bundle::{
+        Authorized, BatchError, BatchValidator, BundleVersion, Flags, MultiVersionBatchValidator,
+        TxVersion,
+    },
     circuit::{OrchardCircuitVersion, ProvingKey, VerifyingKey},
     keys::{FullViewingKey, PreparedIncomingViewingKey, Scope, SpendAuthorizingKey, SpendingKey},
+    memo::{Memo, MemoError},
     note::{ExtractedNoteCommitment, NoteVersion},
     note_encryption::{IronwoodDomain, OrchardDomain},
     tree::{MerkleHashOrchard, MerklePath},
     value::NoteValue,
-    Address, Bundle,
+    Address, Bundle, Proof,
 };
 use rand::rngs::OsRng;
 use shardtree::{store::memory::MemoryShardStore, ShardTree};
@@ -281,7 +285,7 @@ fn ironwood_bundle_helpers_decrypt_and_recover_outputs() {
     assert_eq!(note.version(), NoteVersion::V3);
     assert_eq!(note.value(), NoteValue::from_raw(5000));
     assert_eq!(decrypted_to, recipient);
-    assert_eq!(memo, [0u8; 512]);
+    assert_eq!(memo.as_bytes(), &[0u8; 512]);
 
     let decrypted = bundle.decrypt_outputs_with_keys(&[ivk]);
     assert_eq!(decrypted.len(), 1);
@@ -289,7 +293,7 @@ fn ironwood_bundle_helpers_decrypt_and_recover_outputs() {
     assert_eq!(decrypted[0].2.version(), NoteVersion::V3);
     assert_eq!(decrypted[0].2.value(), NoteValue::from_raw(5000));
     assert_eq!(decrypted[0].3, recipient);
-    assert_eq!(decrypted[0].4, [0u8; 512]);
+    assert_eq!(decrypted[0].4.as_bytes(), &[0u8; 512]);
 
     let (note, recovered_to, memo) = bundle
         .recover_output_with_ovk(action_idx, &ovk)
@@ -297,7 +301,7 @@ fn ironwood_bundle_helpers_decrypt_and_recover_outputs() {
     assert_eq!(note.version(), NoteVersion::V3);
     assert_eq!(note.value(), NoteValue::from_raw(5000));
     assert_eq!(recovered_to, recipient);
-    assert_eq!(memo, [0u8; 512]);
+    assert_eq!(memo.as_bytes(), &[0u8; 512]);
 
     let recovered = bundle.recover_outputs_with_ovks(&[ovk]);
     assert_eq!(recovered.len(), 1);
@@ -305,7 +309,78 @@ fn ironwood_bundle_helpers_decrypt_and_recover_outputs() {
     assert_eq!(recovered[0].2.version(), NoteVersion::V3);
     assert_eq!(recovered[0].2.value(), NoteValue::from_raw(5000));
     assert_eq!(recovered[0].3, recipient);
-    assert_eq!(recovered[0].4, [0u8; 512]);
+    assert_eq!(recovered[0].4.as_bytes(), &[0u8; 512]);
+}
+
+#[test]
+fn bundle_helpers_return_memos() {
+    let mut rng = OsRng;
+    let fvk = FullViewingKey::from(&SpendingKey::from_bytes([0; 32]).unwrap());
+    let ivk = fvk.to_ivk(Scope::External);
+    let ovk = fvk.to_ovk(Scope::External);
+    let memo: Memo = "Thanks for the coffee".parse().unwrap();
+    let anchor = MerkleHashOrchard::empty_root(32.into()).into();
+
+    let mut builder = Builder::new(
+        BundleType::DEFAULT,
+        BundleVersion::orchard_v2(),
+        SHIELDING_FLAGS,
+        anchor,
+    )
+    .unwrap();
+    builder
+        .add_output(
+            Some(ovk.clone()),
+            fvk.address_at(0u32, Scope::External),
+            NoteValue::from_raw(5000),
+            &memo,
+        )
+        .unwrap();
+    // A memo field that claims to hold text but is not valid UTF-8.
+    let mut invalid = [0u8; 512];
+    invalid[..2].copy_from_slice(&[0xC3, 0x28]);
+    builder
+        .add_output(
+            Some(ovk.clone()),
+            fvk.address_at(1u32, Scope::External),
+            NoteValue::from_raw(3000),
+            invalid,
+        )
+        .unwrap();
+    let (bundle, bundle_meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+    let invalid_idx = bundle_meta.output_action_index(1).unwrap();
+
+    let decrypted = bundle.decrypt_outputs_with_keys(&[ivk.clone()]);
+    let recovered = bundle.recover_outputs_with_ovks(&[ovk.clone()]);
+    assert_eq!(decrypted.len(), 2);
+    assert_eq!(recovered.len(), 2);
+    let single = (0..bundle.actions().len()).flat_map(|idx| {
+        let decrypted = bundle.decrypt_output_with_key(idx, &ivk);
+        let recovered = bundle.recover_output_with_ovk(idx, &ovk);
+        [decrypted, recovered]
+            .into_iter()
+            .flatten()
+            .map(move |(_, _, memo)| (idx, memo))
+    });
+    let memos: Vec<_> = decrypted
+        .into_iter()
+        .map(|(idx, _, _, _, memo)| (idx, memo))
+        .chain(
+            recovered
+                .into_iter()
+                .map(|(idx, _, _, _, memo)| (idx, memo)),
+        )
+        .chain(single)
+        .collect();
+    assert_eq!(memos.len(), 8);
+    for (idx, memo_bytes) in memos {
+        if idx == invalid_idx {
+            assert!(matches!(memo_bytes.parse(), Err(MemoError::InvalidUtf8(_))));
+            assert_eq!(memo_bytes.as_bytes(), &invalid);
+        } else {
+            assert_eq!(memo_bytes.parse(), Ok(memo.clone()));
+        }
+    }
 }
 
 // Coinbase bundles disable nonzero-valued spends. From NU6.3, consensus requires
@@ -597,3 +672,115 @@ fn ironwood_post_nu6_3_unrestricted_bundle_proves_and_verifies() {
 
     verify_bundle(&bundle, &post_nu6_3_vk, TxVersion::V6);
 }
+
+// A batch containing bundles with an invalid signature and an invalid proof fails as a whole,
+// and `validate_with_culprits` isolates exactly those bundles by their insertion index.
+#[test]
+fn batch_validator_identifies_culprits() {
+    let mut rng = OsRng;
+    let pk = ProvingKey::build(OrchardCircuitVersion::FixedPostNu6_2);
+    let vk = VerifyingKey::build(OrchardCircuitVersion::FixedPostNu6_2);
+
+    let sk = SpendingKey::from_bytes([0; 32]).unwrap();
+    let recipient = FullViewingKey::from(&sk).address_at(0u32, Scope::External);
+
+    let mut shielding_bundle = || -> (Bundle<Authorized, i64>, [u8; 32]) {
+        let builder =
+            output_only_builder(BundleVersion::orchard_v2(), BundleType::DEFAULT, recipient);
+        let (unauthorized, _) = builder.build(&mut rng).unwrap().unwrap();
+        let sighash = unauthorized
+            .commitment(TxVersion::V5)
+            .expect("bundle flags are representable in this format")
+            .into();
+        let proven = unauthorized.create_proof(&pk, &mut rng).unwrap();
+        (proven.apply_signatures(rng, sighash, &[]).unwrap(), sighash)
+    };
+    let (bundle_a, sighash_a) = shielding_bundle();
+    let (bundle_b, sighash_b) = shielding_bundle();
+
+    // The same bundle with a corrupted (but canonically-sized) proof.
+    let mut proof = bundle_a.authorization().proof().as_ref().to_vec();
+    proof[0] ^= 1;
+    let bad_proof = Bundle::try_from_parts(
+        bundle_a.actions().clone(),
+        *bundle_a.flags(),
+        *bundle_a.value_balance(),
+        *bundle_a.anchor(),
+        Authorized::from_parts(
+            Proof::new(proof),
+            bundle_a.authorization().binding_signature().clone(),
+        ),
+        bundle_a.bundle_version(),
+    )
+    .unwrap();
+
+    let mut validator = BatchValidator::new(&vk);
+    validator.add_bundle(&bundle_a, sighash_a).unwrap();
+    validator.add_bundle(&bundle_a, sighash_b).unwrap();
+    validator.add_bundle(&bad_proof, sighash_a).unwrap();
+    validator.add_bundle(&bundle_b, sighash_b).unwrap();
+    assert_eq!(validator.validate_with_culprits(rng), Err(vec![1, 2]));
+
+    let mut validator = BatchValidator::new(&vk);
+    validator.add_bundle(&bundle_a, sighash_a).unwrap();
+    validator.add_bundle(&bundle_b, sighash_b).unwrap();
+    assert_eq!(validator.validate_with_culprits(rng), Ok(()));
+}
+
+// A multi-version batch validates bundles proven with different circuit versions together,
+// routing each proof to the verifying key for its bundle version.
+#[test]
+fn multi_version_batch_validator_routes_bundles() {
+    let mut rng = OsRng;
+    let fixed_pk = ProvingKey::build(OrchardCircuitVersion::FixedPostNu6_2);
+    let fixed_vk = VerifyingKey::build(OrchardCircuitVersion::FixedPostNu6_2);
+    let post_nu6_3_pk = ProvingKey::build(OrchardCircuitVersion::PostNu6_3);
+    let post_nu6_3_vk = VerifyingKey::build(OrchardCircuitVersion::PostNu6_3);
+
+    let sk = SpendingKey::from_bytes([0; 32]).unwrap();
+    let recipient = FullViewingKey::from(&sk).address_at(0u32, Scope::External);
+
+    let mut shielding_bundle =
+        |bundle_version: BundleVersion, pk: &ProvingKey| -> (Bundle<Authorized, i64>, [u8; 32]) {
+            let builder = output_only_builder(bundle_version, BundleType::DEFAULT, recipient);
+            let (unauthorized, _) = builder.build(&mut rng).unwrap().unwrap();
+            let sighash = unauthorized
+                .commitment(TxVersion::V6)
+                .expect("bundle flags are representable in this format")
+                .into();
+            let proven = unauthorized.create_proof(pk, &mut rng).unwrap();
+            (proven.apply_signatures(rng, sighash, &[]).unwrap(), sighash)
+        };
+    let (orchard_bundle, orchard_sighash) =
+        shielding_bundle(BundleVersion::orchard_v2(), &fixed_pk);
+    let (ironwood_bundle, ironwood_sighash) =
+        shielding_bundle(BundleVersion::ironwood_v3(), &post_nu6_3_pk);
+
+    let mut validator = MultiVersionBatchValidator::new([&fixed_vk, &post_nu6_3_vk]);
+    validator
+        .add_bundle(&orchard_bundle, orchard_sighash)
+        .unwrap();
+    validator
+        .add_bundle(&ironwood_bundle, ironwood_sighash)
+        .unwrap();
+    assert!(validator.validate(rng));
+
+    // A bad signature on either bundle fails the shared signature batch.
+    let mut validator = MultiVersionBatchValidator::new([&fixed_vk, &post_nu6_3_vk]);
+    validator
+        .add_bundle(&orchard_bundle, orchard_sighash)
+        .unwrap();
+    validator
+        .add_bundle(&ironwood_bundle, orchard_sighash)
+        .unwrap();
+    assert!(!validator.validate(rng));
+
+    // A bundle whose circuit version has no key is rejected at insertion.
+    let mut validator = MultiVersionBatchValidator::new([&fixed_vk]);
+    assert_eq!(
+        validator.add_bundle(&ironwood_bundle, ironwood_sighash),
+        Err(BatchError::MissingVerifyingKey(
+            OrchardCircuitVersion::PostNu6_3
+        ))
+    );
+}